@@ -0,0 +1,59 @@
+use crate::error::ApiError;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+    Extension,
+};
+use blog_core::{db::authenticate_token, User};
+
+/// The user behind the request's `Authorization: Bearer <token>` header.
+/// Handlers that take this extractor reject anonymous requests with 401.
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let MaybeUser(user) = MaybeUser::from_request_parts(parts, state).await?;
+        user.map(AuthUser).ok_or(ApiError::Unauthorized)
+    }
+}
+
+/// Like `AuthUser`, for routes that anonymous readers may use too. A request
+/// without an `Authorization` header gets `None`, but a bad token is still
+/// rejected with 401 rather than quietly treated as anonymous.
+pub struct MaybeUser(pub Option<User>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for MaybeUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(header) = parts.headers.get(AUTHORIZATION).cloned() else {
+            return Ok(MaybeUser(None));
+        };
+        let Extension(pool) = Extension::<sqlx::SqlitePool>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| ApiError::Internal(err.into()))?;
+        // Auth schemes are case-insensitive, so `bearer` is as good as `Bearer`
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token)
+            .ok_or(ApiError::Unauthorized)?;
+        match authenticate_token(pool, token.trim()).await? {
+            Some(user) => Ok(MaybeUser(Some(user))),
+            None => Err(ApiError::Unauthorized),
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::auth::{AuthUser, MaybeUser};
+use crate::error::{ApiError, ErrorBody};
+use crate::posts::{get_editable_post, get_visible_post};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::{header::LOCATION, StatusCode};
+use axum::response::IntoResponse;
+use axum::Extension;
+use blog_core::db::{
+    add_comment, delete_comment, get_comment, list_comments, set_comment_status, user_name_taken,
+};
+use blog_core::{
+    BlogPost, Comment, CommentQuery, CommentStatus, CreateComment, FieldError, ModerateComment,
+    User,
+};
+
+/// Whoever may edit a post moderates the comments on it.
+fn is_moderator(viewer: Option<&User>, post: &BlogPost) -> bool {
+    viewer.is_some_and(|user| user.can_edit(&post.author))
+}
+
+/// Approved comments are public. Moderators see everything, and commenters
+/// see their own comments while they wait for moderation.
+fn is_visible(comment: &Comment, viewer: Option<&User>, moderator: bool) -> bool {
+    moderator
+        || comment.status == CommentStatus::Approved
+        || viewer.is_some_and(|user| user.name == comment.author)
+}
+
+fn invalid(field: &str, message: &str) -> ApiError {
+    ApiError::Validation(vec![FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }])
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/comments",
+    tag = "comments",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        CommentQuery,
+    ),
+    responses(
+        (status = 200, description = "The comments the caller may see, oldest first", body = Vec<Comment>),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn list_comments_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    query: Result<axum::extract::Query<CommentQuery>, QueryRejection>,
+) -> Result<axum::Json<Vec<Comment>>, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Query(query) = query?;
+    let post = get_visible_post(pool.clone(), viewer.as_ref(), id).await?;
+    let moderator = is_moderator(viewer.as_ref(), &post);
+    let mut comments = list_comments(pool, id, query.status).await?;
+    comments.retain(|comment| is_visible(comment, viewer.as_ref(), moderator));
+    Ok(axum::Json(comments))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("comment_id" = i32, Path, description = "Comment id"),
+    ),
+    responses(
+        (status = 200, description = "The comment", body = Comment),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post or comment", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn get_comment_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    path: Result<axum::extract::Path<(i32, i32)>, PathRejection>,
+) -> Result<axum::Json<Comment>, ApiError> {
+    let axum::extract::Path((id, comment_id)) = path?;
+    let post = get_visible_post(pool.clone(), viewer.as_ref(), id).await?;
+    let comment = get_comment(pool, id, comment_id).await?;
+    if !is_visible(
+        &comment,
+        viewer.as_ref(),
+        is_moderator(viewer.as_ref(), &post),
+    ) {
+        return Err(ApiError::NotFound);
+    }
+    Ok(axum::Json(comment))
+}
+
+/// Anyone who can read a post may comment on it. Comments from signed-in
+/// users are approved straight away; anonymous ones wait for moderation, and
+/// may not borrow a registered user's name. Responds `201 Created` with the
+/// new comment.
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/comments",
+    tag = "comments",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+    ),
+    request_body = CreateComment,
+    responses(
+        (
+            status = 201,
+            description = "The new comment",
+            body = Comment,
+            headers(("location" = String, description = "URL of the new comment"))
+        ),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+        (status = 422, description = "Some fields are invalid", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn add_comment_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    comment: Result<axum::extract::Json<CreateComment>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Json(comment) = comment?;
+    let comment = comment.validate()?;
+    let post = get_visible_post(pool.clone(), viewer.as_ref(), id).await?;
+
+    let (author, status) = match (&viewer, comment.author) {
+        (Some(user), Some(author)) if author != user.name => {
+            return Err(invalid("author", "must be left out when signed in"));
+        }
+        (Some(user), _) => (user.name.clone(), CommentStatus::Approved),
+        (None, Some(author)) => {
+            if user_name_taken(pool.clone(), &author).await? {
+                return Err(invalid(
+                    "author",
+                    "belongs to a registered user; sign in to use it",
+                ));
+            }
+            (author, CommentStatus::Pending)
+        }
+        (None, None) => return Err(invalid("author", "is required when not signed in")),
+    };
+
+    if let Some(parent_id) = comment.parent_id {
+        let moderator = is_moderator(viewer.as_ref(), &post);
+        match get_comment(pool.clone(), id, parent_id)
+            .await
+            .map_err(ApiError::from)
+        {
+            Ok(parent) if is_visible(&parent, viewer.as_ref(), moderator) => {}
+            Ok(_) | Err(ApiError::NotFound) => {
+                return Err(invalid("parent_id", "is not a comment on this post"));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    let comment_id = add_comment(
+        pool.clone(),
+        id,
+        comment.parent_id,
+        author,
+        comment.body,
+        status,
+    )
+    .await?;
+    let comment = get_comment(pool, id, comment_id).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, format!("/posts/{id}/comments/{comment_id}"))],
+        axum::Json(comment),
+    ))
+}
+
+/// Approves a comment, sends it back to pending, or marks it as spam.
+#[utoipa::path(
+    patch,
+    path = "/posts/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("comment_id" = i32, Path, description = "Comment id"),
+    ),
+    request_body = ModerateComment,
+    responses(
+        (status = 200, description = "The comment", body = Comment),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post or comment", body = ErrorBody),
+        (status = 422, description = "Some fields are invalid", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn moderate_comment_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    path: Result<axum::extract::Path<(i32, i32)>, PathRejection>,
+    moderation: Result<axum::extract::Json<ModerateComment>, JsonRejection>,
+) -> Result<axum::Json<Comment>, ApiError> {
+    let axum::extract::Path((id, comment_id)) = path?;
+    let axum::extract::Json(moderation) = moderation?;
+    get_editable_post(pool.clone(), &user, id).await?;
+    set_comment_status(pool.clone(), id, comment_id, moderation.status).await?;
+    Ok(axum::Json(get_comment(pool, id, comment_id).await?))
+}
+
+/// Moderators may delete any comment, commenters their own. Replies go with
+/// it.
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("comment_id" = i32, Path, description = "Comment id"),
+    ),
+    responses(
+        (status = 204, description = "The comment and its replies were deleted"),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not delete this comment", body = ErrorBody),
+        (status = 404, description = "No such post or comment", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn delete_comment_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    path: Result<axum::extract::Path<(i32, i32)>, PathRejection>,
+) -> Result<StatusCode, ApiError> {
+    let axum::extract::Path((id, comment_id)) = path?;
+    let post = get_visible_post(pool.clone(), Some(&user), id).await?;
+    let comment = get_comment(pool.clone(), id, comment_id).await?;
+    if !is_moderator(Some(&user), &post) && user.name != comment.author {
+        return Err(ApiError::Forbidden);
+    }
+    delete_comment(pool, id, comment_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
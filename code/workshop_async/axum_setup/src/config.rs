@@ -0,0 +1,552 @@
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::HeaderValue;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Read when no `--config` is given, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "blog.toml";
+
+/// Smallest body limit accepted; anything less can't hold a short post.
+const MIN_BODY_BYTES: usize = 1024;
+
+/// Retention periods past this are almost certainly a typo.
+const MAX_RETENTION_DAYS: i64 = 36_500;
+
+/// Everything the server can be told at startup. Built from the defaults,
+/// then `blog.toml` (or `--config`), then environment variables, then the
+/// command line, each overriding the one before.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub runtime: RuntimeConfig,
+    pub log: LogConfig,
+    pub trash: TrashConfig,
+    pub scheduler: SchedulerConfig,
+    pub features: Features,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_address: SocketAddr,
+    /// Largest request body read, in bytes.
+    pub max_body_bytes: usize,
+    /// How long requests in flight get to finish once a shutdown starts.
+    pub drain_timeout_secs: u64,
+    /// Origins allowed to call the API from a browser, such as
+    /// `https://example.com`, or `*` for any. Empty turns CORS off.
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen_address: SocketAddr::from(([127, 0, 0, 1], 3001)),
+            max_body_bytes: crate::MAX_REQUEST_BYTES,
+            drain_timeout_secs: 30,
+            cors_origins: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: "sqlite://blog.db".to_string(),
+            max_connections: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    pub flavor: RuntimeFlavor,
+    /// Workers for the multi-threaded flavor. Unset is one per CPU core.
+    pub worker_threads: Option<usize>,
+    /// Most threads kept for blocking work, such as SQLite's file access.
+    pub max_blocking_threads: usize,
+    /// Threads are named this, with a number after it, as shown by `htop`.
+    pub thread_name: String,
+    /// Tasks polled between checks for new I/O and timer events.
+    pub event_interval: u32,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        // Tokio's own defaults, apart from the name
+        RuntimeConfig {
+            flavor: RuntimeFlavor::default(),
+            worker_threads: None,
+            max_blocking_threads: 512,
+            thread_name: "blog-worker".to_string(),
+            event_interval: 61,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn build(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = match self.flavor {
+            RuntimeFlavor::CurrentThread => tokio::runtime::Builder::new_current_thread(),
+            RuntimeFlavor::MultiThread => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                if let Some(workers) = self.worker_threads {
+                    builder.worker_threads(workers);
+                }
+                builder
+            }
+        };
+        let name = self.thread_name.clone();
+        let next = AtomicUsize::new(0);
+        builder
+            .max_blocking_threads(self.max_blocking_threads)
+            .thread_name_fn(move || format!("{name}-{}", next.fetch_add(1, Ordering::Relaxed)))
+            .event_interval(self.event_interval)
+            .enable_all()
+            .build()
+    }
+}
+
+/// Which tokio scheduler runs the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFlavor {
+    /// Everything on the main thread, as `#[tokio::main(flavor =
+    /// "current_thread")]` does
+    #[default]
+    #[value(name = "current_thread")]
+    CurrentThread,
+    /// A pool of worker threads that steal work from each other
+    #[value(name = "multi_thread")]
+    MultiThread,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One readable line per event
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashConfig {
+    /// Deleted posts are kept in the trash for this many days.
+    pub retention_days: i64,
+    /// How often the trash is checked for posts past their retention.
+    pub purge_interval_secs: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            retention_days: 30,
+            purge_interval_secs: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// How often scheduled posts are checked for being due, and so roughly
+    /// how late they may go out.
+    pub publish_interval_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            publish_interval_secs: 30,
+        }
+    }
+}
+
+/// Parts of the server that can be left out. The JSON API is always on.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    /// The HTML pages under `/blog`, and their stylesheets
+    pub frontend: bool,
+    /// `/feed.rss` and `/feed.atom`
+    pub feeds: bool,
+    /// `/openapi.json` and the Swagger UI at `/docs`
+    pub docs: bool,
+    /// The deprecated paths, such as `/add` and `/update/:id`
+    pub legacy_routes: bool,
+    /// Prometheus metrics at `/metrics`
+    pub metrics: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            frontend: true,
+            feeds: true,
+            docs: true,
+            legacy_routes: true,
+            metrics: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Feature {
+    Frontend,
+    Feeds,
+    Docs,
+    LegacyRoutes,
+    Metrics,
+}
+
+/// Command line flags, each also read from the environment variable named
+/// beside it. Anything left unset keeps the value from the config file.
+#[derive(Parser, Debug, Default)]
+#[clap(name = "axum_setup", version = "1.0")]
+pub struct Args {
+    /// TOML file to read settings from, instead of `blog.toml`
+    #[clap(long, env = "BLOG_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[clap(long, env = "LISTEN_ADDRESS")]
+    pub listen_address: Option<SocketAddr>,
+    #[clap(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+    #[clap(long, env = "DATABASE_MAX_CONNECTIONS")]
+    pub max_connections: Option<u32>,
+    #[clap(long, env = "RUNTIME_FLAVOR")]
+    pub runtime_flavor: Option<RuntimeFlavor>,
+    /// Workers for the multi_thread flavor
+    #[clap(long, env = "WORKER_THREADS")]
+    pub worker_threads: Option<usize>,
+    #[clap(long, env = "MAX_BLOCKING_THREADS")]
+    pub max_blocking_threads: Option<usize>,
+    #[clap(long, env = "THREAD_NAME")]
+    pub thread_name: Option<String>,
+    #[clap(long, env = "EVENT_INTERVAL")]
+    pub event_interval: Option<u32>,
+    #[clap(long, env = "MAX_BODY_BYTES")]
+    pub max_body_bytes: Option<usize>,
+    /// Overridden by `RUST_LOG`, if that is set
+    #[clap(long, env = "LOG_LEVEL")]
+    pub log_level: Option<LogLevel>,
+    #[clap(long, env = "LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    /// Comma-separated origins allowed to call the API from a browser
+    #[clap(long, env = "CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    #[clap(long, env = "TRASH_RETENTION_DAYS")]
+    pub trash_retention_days: Option<i64>,
+    #[clap(long, env = "DRAIN_TIMEOUT_SECS")]
+    pub drain_timeout_secs: Option<u64>,
+    /// Comma-separated features to turn off
+    #[clap(long, env = "DISABLE_FEATURES", value_delimiter = ',')]
+    pub disable: Vec<Feature>,
+}
+
+impl Config {
+    /// Reads the config file named in `args`, or `blog.toml` if there is
+    /// one, applies `args` over it and checks the result.
+    pub fn load(args: Args) -> Result<Config> {
+        let mut config = match &args.config {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Config::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Config::default(),
+        };
+        config.apply(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config in {}", path.display()))
+    }
+
+    fn apply(&mut self, args: Args) {
+        if let Some(address) = args.listen_address {
+            self.server.listen_address = address;
+        }
+        if let Some(url) = args.database_url {
+            self.database.url = url;
+        }
+        if let Some(max) = args.max_connections {
+            self.database.max_connections = max;
+        }
+        if let Some(flavor) = args.runtime_flavor {
+            self.runtime.flavor = flavor;
+        }
+        if let Some(workers) = args.worker_threads {
+            self.runtime.worker_threads = Some(workers);
+        }
+        if let Some(max) = args.max_blocking_threads {
+            self.runtime.max_blocking_threads = max;
+        }
+        if let Some(name) = args.thread_name {
+            self.runtime.thread_name = name;
+        }
+        if let Some(interval) = args.event_interval {
+            self.runtime.event_interval = interval;
+        }
+        if let Some(bytes) = args.max_body_bytes {
+            self.server.max_body_bytes = bytes;
+        }
+        if let Some(level) = args.log_level {
+            self.log.level = level;
+        }
+        if let Some(format) = args.log_format {
+            self.log.format = format;
+        }
+        if let Some(origins) = args.cors_origins {
+            self.server.cors_origins = origins;
+        }
+        if let Some(days) = args.trash_retention_days {
+            self.trash.retention_days = days;
+        }
+        if let Some(secs) = args.drain_timeout_secs {
+            self.server.drain_timeout_secs = secs;
+        }
+        for feature in args.disable {
+            match feature {
+                Feature::Frontend => self.features.frontend = false,
+                Feature::Feeds => self.features.feeds = false,
+                Feature::Docs => self.features.docs = false,
+                Feature::LegacyRoutes => self.features.legacy_routes = false,
+                Feature::Metrics => self.features.metrics = false,
+            }
+        }
+    }
+
+    /// Checks every setting, reporting all the problems at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if !self.database.url.starts_with("sqlite:") {
+            problems.push(format!(
+                "database.url must be a sqlite: URL, not {:?}",
+                self.database.url
+            ));
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        match (self.runtime.flavor, self.runtime.worker_threads) {
+            (_, Some(0)) => {
+                problems.push("runtime.worker_threads must be at least 1".to_string());
+            }
+            (RuntimeFlavor::CurrentThread, Some(_)) => problems.push(
+                "runtime.worker_threads needs runtime.flavor = \"multi_thread\"".to_string(),
+            ),
+            _ => {}
+        }
+        if self.runtime.max_blocking_threads == 0 {
+            problems.push("runtime.max_blocking_threads must be at least 1".to_string());
+        }
+        if self.runtime.thread_name.trim().is_empty() {
+            problems.push("runtime.thread_name must not be empty".to_string());
+        }
+        if self.runtime.event_interval == 0 {
+            problems.push("runtime.event_interval must be at least 1".to_string());
+        }
+        if self.server.max_body_bytes < MIN_BODY_BYTES {
+            problems.push(format!(
+                "server.max_body_bytes must be at least {MIN_BODY_BYTES}"
+            ));
+        }
+        for origin in &self.server.cors_origins {
+            if let Err(problem) = check_origin(origin) {
+                problems.push(format!("server.cors_origins: {problem}"));
+            }
+        }
+        if !(0..=MAX_RETENTION_DAYS).contains(&self.trash.retention_days) {
+            problems.push(format!(
+                "trash.retention_days must be between 0 and {MAX_RETENTION_DAYS}"
+            ));
+        }
+        if self.trash.purge_interval_secs == 0 {
+            problems.push("trash.purge_interval_secs must be at least 1".to_string());
+        }
+        if self.scheduler.publish_interval_secs == 0 {
+            problems.push("scheduler.publish_interval_secs must be at least 1".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            bail!("Invalid configuration:\n  {}", problems.join("\n  "))
+        }
+    }
+
+    pub fn retention(&self) -> chrono::Duration {
+        // In range once validated
+        chrono::Duration::try_days(self.trash.retention_days).unwrap_or_default()
+    }
+
+    pub fn purge_interval(&self) -> Duration {
+        Duration::from_secs(self.trash.purge_interval_secs)
+    }
+
+    pub fn publish_interval(&self) -> Duration {
+        Duration::from_secs(self.scheduler.publish_interval_secs)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.server.drain_timeout_secs)
+    }
+}
+
+/// An origin is a scheme and host, with an optional port and nothing after.
+fn check_origin(origin: &str) -> Result<()> {
+    if origin == "*" {
+        return Ok(());
+    }
+    let rest = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .ok_or_else(|| anyhow!("{origin:?} must start with http:// or https://"))?;
+    if rest.is_empty() || rest.contains('/') {
+        bail!("{origin:?} must be just a scheme and host, such as https://example.com");
+    }
+    HeaderValue::from_str(origin).map_err(|_| anyhow!("{origin:?} isn't a valid origin"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_defaults_are_valid() {
+        Config::default().validate().unwrap();
+    }
+
+    #[test]
+    fn flags_override_the_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [server]
+            listen_address = "0.0.0.0:8080"
+            max_body_bytes = 4096
+
+            [log]
+            format = "json"
+
+            [features]
+            docs = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.listen_address.port(), 8080);
+        assert_eq!(config.database.max_connections, 10);
+        assert_eq!(config.log.format, LogFormat::Json);
+        config.apply(Args {
+            max_body_bytes: Some(8192),
+            disable: vec![Feature::Feeds],
+            ..Args::default()
+        });
+        assert_eq!(config.server.listen_address.port(), 8080);
+        assert_eq!(config.server.max_body_bytes, 8192);
+        assert!(!config.features.docs && !config.features.feeds);
+        assert!(config.features.frontend);
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_settings() {
+        let unknown = toml::from_str::<Config>("[server]\nlisten_adress = \"0.0.0.0:80\"");
+        assert!(unknown.unwrap_err().to_string().contains("listen_adress"));
+        let malformed = toml::from_str::<Config>("[server]\nlisten_address = \"0.0.0:3001\"");
+        assert!(malformed.is_err());
+    }
+
+    #[test]
+    fn worker_threads_need_the_multi_thread_flavor() {
+        let mut config = Config::default();
+        config.runtime.worker_threads = Some(4);
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("multi_thread"), "{message}");
+        config.runtime.flavor = RuntimeFlavor::MultiThread;
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn builds_the_configured_runtime() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [runtime]
+            flavor = "multi_thread"
+            worker_threads = 2
+            thread_name = "test-worker"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        let runtime = config.runtime.build().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+        let worker = runtime.spawn(async { std::thread::current().name().map(str::to_string) });
+        let name = runtime.block_on(worker).unwrap().unwrap();
+        assert!(name.starts_with("test-worker-"), "{name}");
+        config.runtime = RuntimeConfig::default();
+        let runtime = config.runtime.build().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 1);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut config = Config::default();
+        config.database.max_connections = 0;
+        config.server.cors_origins = vec!["example.com".to_string(), "https://ok.test".to_string()];
+        config.trash.retention_days = -1;
+        let message = config.validate().unwrap_err().to_string();
+        assert!(message.contains("max_connections"), "{message}");
+        assert!(message.contains("\"example.com\""), "{message}");
+        assert!(!message.contains("ok.test"), "{message}");
+        assert!(message.contains("retention_days"), "{message}");
+    }
+}
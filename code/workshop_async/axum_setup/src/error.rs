@@ -0,0 +1,191 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header::WWW_AUTHENTICATE, HeaderValue, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use blog_core::db::{InvalidQuery, VersionConflict};
+use blog_core::FieldError;
+use serde::Serialize;
+use sqlx::error::ErrorKind;
+use utoipa::ToSchema;
+
+/// Errors returned by the route handlers. Every variant is rendered as a
+/// JSON body of the form `{ "error": { "code": "...", "message": "..." } }`
+/// so that clients can parse failures the same way they parse successes.
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound,
+    /// No valid bearer token was supplied.
+    Unauthorized,
+    /// The caller is authenticated but may not touch this resource.
+    Forbidden,
+    Conflict(String),
+    /// `If-Match` named a version of the post that is no longer current.
+    PreconditionFailed,
+    Unprocessable(String),
+    /// The request body parsed, but some fields break the rules.
+    Validation(Vec<FieldError>),
+    /// An extractor (path, query, JSON body) refused the request before it
+    /// reached the handler, or the query was well-formed but unusable.
+    BadRequest(StatusCode, String),
+    Internal(anyhow::Error),
+}
+
+/// The body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail {
+    #[schema(value_type = String, example = "not_found")]
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            ApiError::Unprocessable(_) | ApiError::Validation(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ApiError::BadRequest(status, _) => *status,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound => "not_found",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden => "forbidden",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PreconditionFailed => "precondition_failed",
+            ApiError::Unprocessable(_) => "unprocessable",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::BadRequest(..) => "bad_request",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::NotFound => "The requested resource was not found".to_string(),
+            ApiError::Unauthorized => "A valid bearer token is required".to_string(),
+            ApiError::Forbidden => "You may only change your own posts and comments".to_string(),
+            ApiError::PreconditionFailed => {
+                "The post has changed since you fetched it".to_string()
+            }
+            ApiError::Conflict(msg)
+            | ApiError::Unprocessable(msg)
+            | ApiError::BadRequest(_, msg) => msg.clone(),
+            ApiError::Validation(_) => "One or more fields are invalid".to_string(),
+            // Don't leak database internals to the client
+            ApiError::Internal(_) => "Internal server error".to_string(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => ApiError::NotFound,
+            // The constraint text names tables and columns, so it is logged
+            // here and the client gets a fixed message
+            sqlx::Error::Database(db_err) => match db_err.kind() {
+                ErrorKind::UniqueViolation | ErrorKind::ForeignKeyViolation => {
+                    tracing::warn!(error = db_err.message(), "constraint violation");
+                    ApiError::Conflict(
+                        "The request conflicts with the current state of the resource"
+                            .to_string(),
+                    )
+                }
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                    tracing::warn!(error = db_err.message(), "constraint violation");
+                    ApiError::Unprocessable("The request contains an invalid value".to_string())
+                }
+                _ => ApiError::Internal(err.into()),
+            },
+            _ => ApiError::Internal(err.into()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        // The data access functions return anyhow; recover the sqlx error
+        // underneath so that it can be mapped to the right status code.
+        let err = match err.downcast::<sqlx::Error>() {
+            Ok(sqlx_err) => return sqlx_err.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<VersionConflict>() {
+            Ok(_) => return ApiError::PreconditionFailed,
+            Err(err) => err,
+        };
+        match err.downcast::<InvalidQuery>() {
+            Ok(InvalidQuery(msg)) => ApiError::BadRequest(StatusCode::BAD_REQUEST, msg),
+            Err(err) => ApiError::Internal(err),
+        }
+    }
+}
+
+impl From<Vec<FieldError>> for ApiError {
+    fn from(fields: Vec<FieldError>) -> Self {
+        ApiError::Validation(fields)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::BadRequest(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::BadRequest(rejection.status(), rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        ApiError::BadRequest(rejection.status(), rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        if let ApiError::Internal(err) = &self {
+            tracing::error!(error = ?err, "internal error");
+        }
+        let (status, code, message) = (self.status(), self.code(), self.message());
+        let fields = match self {
+            ApiError::Validation(fields) => fields,
+            _ => Vec::new(),
+        };
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code,
+                message,
+                fields,
+            },
+        };
+        let mut response = (status, Json(body)).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
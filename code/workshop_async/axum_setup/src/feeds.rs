@@ -0,0 +1,334 @@
+use crate::error::{ApiError, ErrorBody};
+use axum::extract::rejection::QueryRejection;
+use axum::http::{
+    header::{CONTENT_TYPE, ETAG, HOST, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use blog_core::db::{get_rendered_body, list_blog_posts};
+use blog_core::{BlogPost, ListQuery, PostStatus, SortField, SortOrder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// Posts in each feed. Readers poll, so only the newest ones are needed.
+const FEED_SIZE: u32 = 20;
+
+const FEED_TITLE: &str = "Blog";
+
+/// Query string of the feed routes, narrowing them to one author or tag.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedQuery {
+    /// Only posts by this author.
+    author: Option<String>,
+    /// Only posts with this tag.
+    tag: Option<String>,
+}
+
+/// The latest published posts for a feed, and the page of the blog they
+/// come from.
+struct Feed {
+    base: String,
+    title: String,
+    link: String,
+    posts: Vec<(BlogPost, String)>,
+}
+
+impl Feed {
+    /// When the feed last changed: the most recent publication or edit of
+    /// any post in it, truncated to the second as HTTP dates are.
+    fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.posts
+            .iter()
+            .map(|(post, _)| post.updated_at.max(post.published_at()))
+            .max()
+            .and_then(|time| DateTime::from_timestamp(time.timestamp(), 0))
+    }
+
+    /// A weak entity tag over the posts in the feed and their versions.
+    /// Unlike the date, it changes when a post drops out of the feed, because
+    /// it was trashed or unpublished. FNV-1a gives the same tag from one
+    /// build to the next, so readers' copies stay valid across deploys.
+    fn etag(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        write(self.base.as_bytes());
+        for (post, _) in &self.posts {
+            write(&post.id.to_le_bytes());
+            write(&post.version.to_le_bytes());
+        }
+        format!("W/\"{hash:016x}\"")
+    }
+
+    fn post_link(&self, post: &BlogPost) -> String {
+        format!("{}/blog/posts/{}", self.base, post.id)
+    }
+}
+
+/// The scheme and host the request came in on, so that feed links are
+/// absolute. `X-Forwarded-Proto` is honoured for servers behind a proxy.
+fn base_url(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    let host = header(HOST.as_str()).unwrap_or("localhost");
+    format!("{scheme}://{host}")
+}
+
+/// Percent-encodes a path segment. Author names may contain spaces, and the
+/// query can ask for any tag, so neither can go in a URL as it is.
+fn encode_path(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+async fn load_feed(
+    pool: sqlx::SqlitePool,
+    headers: &HeaderMap,
+    query: FeedQuery,
+) -> Result<Feed, ApiError> {
+    let base = base_url(headers);
+    let (title, link) = match (&query.author, &query.tag) {
+        (Some(author), _) => (
+            format!("{FEED_TITLE}: posts by {author}"),
+            format!("{base}/blog/authors/{}", encode_path(author)),
+        ),
+        (None, Some(tag)) => (
+            format!("{FEED_TITLE}: posts tagged #{tag}"),
+            format!("{base}/blog/tags/{}", encode_path(tag)),
+        ),
+        (None, None) => (FEED_TITLE.to_string(), format!("{base}/blog")),
+    };
+    let list = ListQuery {
+        limit: Some(FEED_SIZE),
+        sort: Some(SortField::Published),
+        order: Some(SortOrder::Desc),
+        status: Some(PostStatus::Published),
+        author: query.author,
+        tag: query.tag,
+        ..ListQuery::default()
+    };
+    // Feeds are public, so they never include anyone's drafts
+    let mut posts = Vec::new();
+    for post in list_blog_posts(pool.clone(), &list, None).await?.items {
+        let html = get_rendered_body(pool.clone(), &post).await?;
+        posts.push((post, html));
+    }
+    Ok(Feed {
+        base,
+        title,
+        link,
+        posts,
+    })
+}
+
+/// Whether the reader's copy is still current. `If-None-Match` is checked
+/// if it was sent, comparing entity tags weakly; only otherwise is the copy
+/// dated by `If-Modified-Since`, as RFC 9110 lays down.
+fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    if headers.contains_key(IF_NONE_MATCH) {
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+        return headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag));
+    }
+    let since = headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    match (since, last_modified) {
+        (Some(since), Some(last_modified)) => last_modified <= since,
+        _ => false,
+    }
+}
+
+/// The feed document with its `ETag` and `Last-Modified` date, or an empty
+/// `304 Not Modified` if the reader already has it.
+fn feed_response(
+    headers: &HeaderMap,
+    feed: &Feed,
+    content_type: &'static str,
+    document: impl FnOnce(&Feed) -> String,
+) -> Response {
+    let etag = feed.etag();
+    let last_modified = feed.last_modified();
+    let mut response = if not_modified(headers, &etag, last_modified) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(CONTENT_TYPE, HeaderValue::from_static(content_type))],
+            document(feed),
+        )
+            .into_response()
+    };
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(ETAG, value);
+    }
+    if let Some(time) = last_modified {
+        let date = time.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            response.headers_mut().insert(LAST_MODIFIED, value);
+        }
+    }
+    response
+}
+
+fn rss_document(feed: &Feed) -> String {
+    let items = feed
+        .posts
+        .iter()
+        .map(|(post, html)| rss::Item {
+            title: Some(post.title.clone()),
+            link: Some(feed.post_link(post)),
+            description: Some(html.clone()),
+            // RSS asks for an email address, which authors don't have, but
+            // readers show whatever is given
+            author: Some(post.author.clone()),
+            categories: post
+                .tags
+                .iter()
+                .map(|tag| rss::Category {
+                    name: tag.clone(),
+                    domain: None,
+                })
+                .collect(),
+            guid: Some(rss::Guid {
+                value: feed.post_link(post),
+                permalink: true,
+            }),
+            pub_date: Some(post.published_at().to_rfc2822()),
+            ..rss::Item::default()
+        })
+        .collect();
+    let channel = rss::Channel {
+        title: feed.title.clone(),
+        link: feed.link.clone(),
+        description: format!("The latest posts from {}", feed.title),
+        last_build_date: feed.last_modified().map(|time| time.to_rfc2822()),
+        items,
+        ..rss::Channel::default()
+    };
+    channel.to_string()
+}
+
+fn atom_document(feed: &Feed) -> String {
+    use atom_syndication::{Category, Content, Entry, Link, Person};
+    let link = |href: String, rel: &str| Link {
+        href,
+        rel: rel.to_string(),
+        ..Link::default()
+    };
+    let entries = feed
+        .posts
+        .iter()
+        .map(|(post, html)| Entry {
+            title: post.title.clone().into(),
+            id: feed.post_link(post),
+            updated: post.updated_at.into(),
+            published: Some(post.published_at().into()),
+            authors: vec![Person {
+                name: post.author.clone(),
+                ..Person::default()
+            }],
+            categories: post
+                .tags
+                .iter()
+                .map(|tag| Category {
+                    term: tag.clone(),
+                    ..Category::default()
+                })
+                .collect(),
+            links: vec![link(feed.post_link(post), "alternate")],
+            content: Some(Content {
+                value: Some(html.clone()),
+                content_type: Some("html".to_string()),
+                ..Content::default()
+            }),
+            ..Entry::default()
+        })
+        .collect();
+    let atom = atom_syndication::Feed {
+        title: feed.title.clone().into(),
+        id: feed.link.clone(),
+        // An empty feed has never changed; the epoch is as good as any date
+        updated: feed.last_modified().unwrap_or_default().into(),
+        links: vec![link(feed.link.clone(), "alternate")],
+        entries,
+        ..atom_syndication::Feed::default()
+    };
+    atom.to_string()
+}
+
+/// RSS 2.0 feed of the latest published posts, optionally by one `author`
+/// or with one `tag`.
+#[utoipa::path(
+    get,
+    path = "/feed.rss",
+    tag = "feeds",
+    params(
+        FeedQuery,
+    ),
+    responses(
+        (status = 200, description = "RSS 2.0 feed", body = String, content_type = "application/rss+xml"),
+        (status = 304, description = "The feed still matches `If-None-Match`, or hasn't changed since `If-Modified-Since`"),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+    )
+)]
+pub async fn rss_feed_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    headers: HeaderMap,
+    query: Result<axum::extract::Query<FeedQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let axum::extract::Query(query) = query?;
+    let feed = load_feed(pool, &headers, query).await?;
+    Ok(feed_response(
+        &headers,
+        &feed,
+        "application/rss+xml; charset=utf-8",
+        rss_document,
+    ))
+}
+
+/// The same posts as `rss_feed_handler`, as an Atom feed.
+#[utoipa::path(
+    get,
+    path = "/feed.atom",
+    tag = "feeds",
+    params(
+        FeedQuery,
+    ),
+    responses(
+        (status = 200, description = "Atom feed", body = String, content_type = "application/atom+xml"),
+        (status = 304, description = "The feed still matches `If-None-Match`, or hasn't changed since `If-Modified-Since`"),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+    )
+)]
+pub async fn atom_feed_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    headers: HeaderMap,
+    query: Result<axum::extract::Query<FeedQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let axum::extract::Query(query) = query?;
+    let feed = load_feed(pool, &headers, query).await?;
+    Ok(feed_response(
+        &headers,
+        &feed,
+        "application/atom+xml; charset=utf-8",
+        atom_document,
+    ))
+}
@@ -0,0 +1,240 @@
+use crate::error::ApiError;
+use askama::Template;
+use axum::extract::rejection::{PathRejection, QueryRejection};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Extension;
+use blog_core::db::{get_blog_post, get_rendered_body, list_blog_posts, list_comments};
+use blog_core::{BlogPost, Comment, CommentStatus, ListQuery, SortField, SortOrder};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Posts per page on the index, author and tag pages.
+const PAGE_SIZE: u32 = 10;
+
+#[derive(Template)]
+#[template(path = "list.html")]
+struct ListPage {
+    heading: String,
+    posts: Vec<BlogPost>,
+    page: u32,
+    has_next: bool,
+}
+
+#[derive(Template)]
+#[template(path = "post.html")]
+struct PostPage {
+    post: BlogPost,
+    body: String,
+    comments: Vec<(usize, Comment)>,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorPage {
+    heading: String,
+    message: String,
+}
+
+/// Errors from the HTML pages. They have the same causes and status codes
+/// as `ApiError`, but are shown to the reader as a page rather than JSON.
+pub enum PageError {
+    NotFound,
+    Other(ApiError),
+}
+
+impl From<ApiError> for PageError {
+    fn from(err: ApiError) -> Self {
+        match err {
+            ApiError::NotFound => PageError::NotFound,
+            err => PageError::Other(err),
+        }
+    }
+}
+
+impl From<anyhow::Error> for PageError {
+    fn from(err: anyhow::Error) -> Self {
+        ApiError::from(err).into()
+    }
+}
+
+impl From<askama::Error> for PageError {
+    fn from(err: askama::Error) -> Self {
+        PageError::Other(ApiError::Internal(err.into()))
+    }
+}
+
+/// A path that doesn't parse, like `/blog/posts/abc`, names no page.
+impl From<PathRejection> for PageError {
+    fn from(_: PathRejection) -> Self {
+        PageError::NotFound
+    }
+}
+
+impl From<QueryRejection> for PageError {
+    fn from(err: QueryRejection) -> Self {
+        ApiError::from(err).into()
+    }
+}
+
+impl IntoResponse for PageError {
+    fn into_response(self) -> Response {
+        let (status, page) = match self {
+            PageError::NotFound => (
+                axum::http::StatusCode::NOT_FOUND,
+                ErrorPage {
+                    heading: "Page not found".to_string(),
+                    message: "There's nothing here. The post may have been moved or taken down."
+                        .to_string(),
+                },
+            ),
+            PageError::Other(err) => {
+                // Let the API error pick the status (and log internal errors)
+                let status = err.into_response().status();
+                let reason = status.canonical_reason().unwrap_or("Error");
+                let message = if status.is_client_error() {
+                    "This page can't be shown for the address you asked for."
+                } else {
+                    "Sorry, something went wrong showing this page."
+                };
+                (
+                    status,
+                    ErrorPage {
+                        heading: reason.to_string(),
+                        message: message.to_string(),
+                    },
+                )
+            }
+        };
+        match page.render() {
+            Ok(html) => (status, Html(html)).into_response(),
+            Err(_) => status.into_response(),
+        }
+    }
+}
+
+fn render(template: impl Template) -> Result<Html<String>, PageError> {
+    Ok(Html(template.render()?))
+}
+
+/// Query string of the paginated pages; pages are numbered from 1.
+#[derive(Deserialize)]
+pub struct PageQuery {
+    page: Option<u32>,
+}
+
+impl PageQuery {
+    fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+}
+
+/// One page of published posts, most recently published first. A page past the end is a
+/// 404, and so is the first page of an empty author or tag (`filtered`).
+async fn list_page(
+    pool: sqlx::SqlitePool,
+    heading: String,
+    query: ListQuery,
+    page: u32,
+    filtered: bool,
+) -> Result<Html<String>, PageError> {
+    let query = ListQuery {
+        limit: Some(PAGE_SIZE),
+        offset: Some((page - 1).saturating_mul(PAGE_SIZE)),
+        sort: Some(SortField::Published),
+        order: Some(SortOrder::Desc),
+        ..query
+    };
+    // The pages are for readers, so they only ever show what is public
+    let result = list_blog_posts(pool, &query, None).await?;
+    if result.items.is_empty() && (page > 1 || filtered) {
+        return Err(PageError::NotFound);
+    }
+    render(ListPage {
+        heading,
+        posts: result.items,
+        page,
+        has_next: result.next_cursor.is_some(),
+    })
+}
+
+pub async fn index_page(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    query: Result<axum::extract::Query<PageQuery>, QueryRejection>,
+) -> Result<Html<String>, PageError> {
+    let axum::extract::Query(query) = query?;
+    let heading = "Latest posts".to_string();
+    list_page(pool, heading, ListQuery::default(), query.page(), false).await
+}
+
+pub async fn author_page(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    name: Result<axum::extract::Path<String>, PathRejection>,
+    query: Result<axum::extract::Query<PageQuery>, QueryRejection>,
+) -> Result<Html<String>, PageError> {
+    let axum::extract::Path(name) = name?;
+    let axum::extract::Query(query) = query?;
+    let heading = format!("Posts by {name}");
+    let filter = ListQuery {
+        author: Some(name),
+        ..ListQuery::default()
+    };
+    list_page(pool, heading, filter, query.page(), true).await
+}
+
+pub async fn tag_page(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    tag: Result<axum::extract::Path<String>, PathRejection>,
+    query: Result<axum::extract::Query<PageQuery>, QueryRejection>,
+) -> Result<Html<String>, PageError> {
+    let axum::extract::Path(tag) = tag?;
+    let axum::extract::Query(query) = query?;
+    let heading = format!("Posts tagged #{tag}");
+    let filter = ListQuery {
+        tag: Some(tag),
+        ..ListQuery::default()
+    };
+    list_page(pool, heading, filter, query.page(), true).await
+}
+
+/// Orders comments so that each one is followed by its replies, paired with
+/// how deeply it is nested. Replies to comments that aren't shown are moved
+/// up to the top level.
+fn thread(mut comments: Vec<Comment>) -> Vec<(usize, Comment)> {
+    fn walk(all: &[Comment], parent: Option<i32>, depth: usize, out: &mut Vec<(usize, Comment)>) {
+        for comment in all.iter().filter(|comment| comment.parent_id == parent) {
+            out.push((depth, comment.clone()));
+            walk(all, Some(comment.id), depth + 1, out);
+        }
+    }
+    let ids: HashSet<i32> = comments.iter().map(|comment| comment.id).collect();
+    for comment in &mut comments {
+        comment.parent_id = comment.parent_id.filter(|parent| ids.contains(parent));
+    }
+    let mut threaded = Vec::with_capacity(comments.len());
+    walk(&comments, None, 0, &mut threaded);
+    threaded
+}
+
+/// A published post with its approved comments.
+pub async fn post_page(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+) -> Result<Html<String>, PageError> {
+    let axum::extract::Path(id) = id?;
+    let post = get_blog_post(pool.clone(), id).await?;
+    if !post.is_visible_to(None) {
+        return Err(PageError::NotFound);
+    }
+    let body = get_rendered_body(pool.clone(), &post).await?;
+    let comments = list_comments(pool, id, Some(CommentStatus::Approved)).await?;
+    render(PostPage {
+        post,
+        body,
+        comments: thread(comments),
+    })
+}
+
+/// Anything under the pages' prefix that isn't a page.
+pub async fn not_found_page() -> PageError {
+    PageError::NotFound
+}
@@ -0,0 +1,149 @@
+mod auth;
+mod comments;
+pub mod config;
+mod error;
+mod feeds;
+mod frontend;
+pub mod monitoring;
+mod openapi;
+mod posts;
+mod precondition;
+mod revisions;
+mod routes;
+mod scheduler;
+mod server;
+mod tags;
+pub mod telemetry;
+mod trash;
+
+use axum::extract::DefaultBodyLimit;
+use axum::http::{header, HeaderValue, Method};
+use axum::response::{Redirect, Response};
+use axum::Extension;
+use config::Config;
+use frontend::{author_page, index_page, not_found_page, post_page, tag_page};
+use monitoring::{metrics_handler, track_requests};
+use openapi::ApiDoc;
+use posts::{
+    add_blog_post_handler, delete_blog_post_handler, get_blog_post_handler,
+    get_blog_posts_handler, update_blog_post_handler,
+};
+use routes::{api_routes, feed_routes};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::services::ServeDir;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+pub use scheduler::publish_scheduled;
+pub use server::serve;
+pub use trash::purge_trash;
+
+/// Default for the largest request body the router will read. Generous
+/// enough for a post at `MAX_BODY_LEN` characters of multi-byte text, plus
+/// the JSON around it.
+pub(crate) const MAX_REQUEST_BYTES: usize = 4 * blog_core::MAX_BODY_LEN + 1024;
+
+/// Stylesheets for the HTML pages. Found from the crate rather than the
+/// working directory, since the templates are compiled in from there too.
+const STATIC_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/static");
+
+async fn say_hello() -> &'static str {
+    "Hello, World!"
+}
+
+/// Marks responses from the pre-REST paths, which will be removed in a
+/// future release, with a `Deprecation` header.
+async fn mark_deprecated(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
+}
+
+/// Lets browsers on `origins` call the API. The API takes a bearer token
+/// rather than cookies, so credentials are never allowed.
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        // Checked when the config was loaded
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+            telemetry::REQUEST_ID,
+        ])
+        .expose_headers([header::ETAG, header::LOCATION, telemetry::REQUEST_ID])
+}
+
+/// The whole server, sharing `pool`: the API, and whichever of the HTML
+/// pages, feeds, API docs, deprecated paths and metrics `config` turns on.
+pub fn app(pool: sqlx::SqlitePool, config: &Config) -> axum::Router {
+    use axum::routing::{get, post};
+    let features = &config.features;
+    let mut app = axum::Router::new()
+        .route("/hello", get(say_hello))
+        .merge(api_routes());
+    if features.feeds {
+        app = app.merge(feed_routes());
+    }
+    if features.frontend {
+        // HTML pages for reading the blog in a browser
+        let frontend = axum::Router::new()
+            .route("/", get(index_page))
+            .route("/posts/:id", get(post_page))
+            .route("/authors/:name", get(author_page))
+            .route("/tags/:tag", get(tag_page))
+            .fallback(not_found_page);
+        app = app
+            .nest("/blog", frontend)
+            .route("/blog/", get(|| async { Redirect::permanent("/blog") }))
+            .nest_service("/static", ServeDir::new(STATIC_DIR));
+    }
+    if features.docs {
+        app = app.merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()));
+    }
+    if features.legacy_routes {
+        let legacy = axum::Router::new()
+            .route("/", get(get_blog_posts_handler))
+            .route("/:id", get(get_blog_post_handler))
+            .route("/add", post(add_blog_post_handler))
+            .route("/update/:id", post(update_blog_post_handler))
+            .route(
+                "/delete/:id",
+                post(delete_blog_post_handler).delete(delete_blog_post_handler),
+            )
+            .layer(axum::middleware::map_response(mark_deprecated));
+        app = app.merge(legacy);
+    }
+    if features.metrics {
+        // Only routed requests are counted, each under the route it matched
+        app = app
+            .route("/metrics", get(metrics_handler))
+            .route_layer(axum::middleware::from_fn(track_requests));
+    }
+    let app = app
+        .layer(DefaultBodyLimit::max(config.server.max_body_bytes))
+        .layer(Extension(pool));
+    let app = if config.server.cors_origins.is_empty() {
+        app
+    } else {
+        app.layer(cors_layer(&config.server.cors_origins))
+    };
+    telemetry::trace_requests(app)
+}
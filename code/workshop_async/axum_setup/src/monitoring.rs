@@ -0,0 +1,115 @@
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::span;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Histogram buckets, in seconds: from well under a millisecond for a
+/// cached page up to the few seconds of a badly contended pool.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Spans from this module are the query functions, timed by `QueryTimer`.
+pub const QUERY_TARGET: &str = "blog_core::db";
+
+/// The process-wide recorder, installed the first time it is asked for.
+fn prometheus() -> &'static PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets(LATENCY_BUCKETS)
+            .expect("the buckets are not empty")
+            .install_recorder()
+            .expect("no other metrics recorder is installed")
+    })
+}
+
+/// Installs the recorder, so that anything recorded before the first
+/// scrape is kept.
+pub fn init_metrics() {
+    prometheus();
+}
+
+/// Counts each request, and how long it took, by method, route and status.
+/// The route is the pattern it matched, such as `/posts/:id`, so that the
+/// series don't grow with every post.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let response = next.run(request).await;
+    let status = response.status().as_u16().to_string();
+    let labels = [("method", method), ("route", route), ("status", status)];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(started.elapsed());
+    response
+}
+
+/// Prometheus text format. The pool and runtime are sampled now, since
+/// they describe the moment rather than add up over time.
+pub async fn metrics_handler(Extension(pool): Extension<sqlx::SqlitePool>) -> impl IntoResponse {
+    let idle = pool.num_idle() as f64;
+    gauge!("db_pool_connections", "state" => "idle").set(idle);
+    gauge!("db_pool_connections", "state" => "in_use").set(pool.size() as f64 - idle);
+    gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+
+    let runtime = tokio::runtime::Handle::current().metrics();
+    gauge!("tokio_workers").set(runtime.num_workers() as f64);
+    gauge!("tokio_alive_tasks").set(runtime.num_alive_tasks() as f64);
+    gauge!("tokio_global_queue_depth").set(runtime.global_queue_depth() as f64);
+    for worker in 0..runtime.num_workers() {
+        let worker_label = [("worker", worker.to_string())];
+        // Totals since startup; take a rate() to get the share of time busy
+        gauge!("tokio_worker_busy_seconds", &worker_label)
+            .set(runtime.worker_total_busy_duration(worker));
+        counter!("tokio_worker_parks_total", &worker_label)
+            .absolute(runtime.worker_park_count(worker));
+    }
+
+    let handle = prometheus();
+    handle.run_upkeep();
+    (
+        [("content-type", "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+}
+
+/// Records how long each query function took, from the `tracing` span it
+/// runs in, as `db_query_duration_seconds` labelled with its name.
+pub struct QueryTimer;
+
+struct Started(Instant);
+
+impl<S> Layer<S> for QueryTimer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let elapsed = match span.extensions().get::<Started>() {
+            Some(Started(started)) => started.elapsed(),
+            None => Duration::ZERO,
+        };
+        histogram!("db_query_duration_seconds", "query" => span.name()).record(elapsed);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{comments, feeds, posts, revisions, tags, trash};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// The OpenAPI description of `routes::api_routes`, served at
+/// `/openapi.json`. Schemas for the request and response bodies come from
+/// `blog_core`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Blog API"),
+    paths(
+        posts::get_blog_posts_handler,
+        posts::search_blog_posts_handler,
+        posts::get_blog_post_handler,
+        posts::add_blog_post_handler,
+        posts::update_blog_post_handler,
+        posts::patch_blog_post_handler,
+        posts::delete_blog_post_handler,
+        revisions::list_revisions_handler,
+        revisions::get_revision_handler,
+        revisions::diff_revisions_handler,
+        revisions::restore_revision_handler,
+        comments::list_comments_handler,
+        comments::get_comment_handler,
+        comments::add_comment_handler,
+        comments::moderate_comment_handler,
+        comments::delete_comment_handler,
+        trash::list_trash_handler,
+        trash::undelete_blog_post_handler,
+        trash::purge_blog_post_handler,
+        tags::list_tags_handler,
+        feeds::rss_feed_handler,
+        feeds::atom_feed_handler,
+    ),
+    components(schemas(
+        blog_core::PostStatus,
+        blog_core::SortField,
+        blog_core::SortOrder,
+        blog_core::CommentStatus,
+        blog_core::PostFormat,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "posts", description = "Reading and writing posts"),
+        (name = "revisions", description = "The history of each post"),
+        (name = "comments", description = "Threaded comments, and their moderation"),
+        (name = "trash", description = "Deleted posts, until they are purged"),
+        (name = "tags", description = "Tags in use"),
+        (name = "feeds", description = "RSS and Atom feeds of published posts"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Bearer tokens, as issued by `blog_admin issue-token`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiDoc;
+    use crate::config::Config;
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use blog_core::db::{
+        add_comment, add_user, delete_blog_post, get_blog_post, get_connection_pool,
+        issue_token, run_migrations, update_blog_post,
+    };
+    use blog_core::CommentStatus;
+    use std::collections::BTreeMap;
+    use tower::ServiceExt;
+    use utoipa::OpenApi;
+
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+    ];
+
+    /// The methods the spec gives for each path.
+    fn documented() -> BTreeMap<String, Vec<Method>> {
+        let spec = ApiDoc::openapi();
+        let mut routes = BTreeMap::new();
+        for (path, item) in &spec.paths.paths {
+            let operations = [
+                (Method::GET, &item.get),
+                (Method::POST, &item.post),
+                (Method::PUT, &item.put),
+                (Method::PATCH, &item.patch),
+                (Method::DELETE, &item.delete),
+            ];
+            let methods = operations
+                .into_iter()
+                .filter(|(_, operation)| operation.is_some())
+                .map(|(method, _)| method)
+                .collect();
+            routes.insert(path.clone(), methods);
+        }
+        routes
+    }
+
+    /// A documented path with its parameters filled in. Post 1 has a
+    /// comment and a revision, and post 2 is in the trash.
+    fn uri(path: &str) -> String {
+        let trashed = path.starts_with("/trash/") || path.ends_with("/undelete");
+        let uri = path
+            .replace("{id}", if trashed { "2" } else { "1" })
+            .replace("{version}", "1")
+            .replace("{comment_id}", "1");
+        assert!(!uri.contains('{'), "no value for the parameters in {path}");
+        uri
+    }
+
+    /// Sends `method` to `path` on the whole app, as an admin, over a fresh
+    /// database, returning the status and body.
+    async fn send(method: Method, path: &str) -> (StatusCode, String) {
+        let pool = get_connection_pool("sqlite::memory:").await.unwrap();
+        run_migrations(pool.clone()).await.unwrap();
+        let admin = add_user(pool.clone(), "admin".to_string(), true).await.unwrap();
+        let token = issue_token(pool.clone(), admin, None).await.unwrap().token;
+        add_comment(
+            pool.clone(),
+            1,
+            None,
+            "bob".to_string(),
+            "Nice".to_string(),
+            CommentStatus::Approved,
+        )
+        .await
+        .unwrap();
+        let mut post = get_blog_post(pool.clone(), 1).await.unwrap();
+        post.title = "Edited".to_string();
+        update_blog_post(pool.clone(), &post, None).await.unwrap();
+        delete_blog_post(pool.clone(), 2, None).await.unwrap();
+
+        let request = Request::builder()
+            .method(method)
+            .uri(uri(path))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = crate::app(pool, &Config::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn every_documented_route_is_routed() {
+        for (path, methods) in documented() {
+            for method in methods {
+                let (status, body) = send(method.clone(), &path).await;
+                assert!(
+                    status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED,
+                    "{method} {path} is documented but answered {status}: {body}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn every_route_is_documented() {
+        // Axum can't list its routes, so try the methods the spec leaves
+        // out on each of its paths
+        for (path, methods) in documented() {
+            for method in METHODS.into_iter().filter(|method| !methods.contains(method)) {
+                let (status, body) = send(method.clone(), &path).await;
+                assert_eq!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{method} {path} is routed but not documented: {body}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_schema_reference_resolves() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = &spec["components"]["schemas"];
+        let mut pending = vec![&spec];
+        while let Some(value) = pending.pop() {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(serde_json::Value::String(reference)) = map.get("$ref") {
+                        let name = reference.trim_start_matches("#/components/schemas/");
+                        assert!(schemas.get(name).is_some(), "{reference} is not defined");
+                    }
+                    pending.extend(map.values());
+                }
+                serde_json::Value::Array(values) => pending.extend(values),
+                _ => {}
+            }
+        }
+    }
+}
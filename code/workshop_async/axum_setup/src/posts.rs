@@ -0,0 +1,338 @@
+use crate::auth::{AuthUser, MaybeUser};
+use crate::error::{ApiError, ErrorBody};
+use crate::precondition::{etag, IfMatch};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::http::{
+    header::{ACCEPT, ETAG, LOCATION, VARY},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use chrono::Utc;
+use blog_core::db::{
+    add_blog_post, delete_blog_post, get_blog_post, get_rendered_body, list_blog_posts, search_blog_posts,
+    update_blog_post,
+};
+use blog_core::{
+    BlogPost, CreatePost, ListQuery, Page, PatchPost, PostFormat, PostQuery, Publication,
+    SearchHit, SearchQuery, UpdatePost, User,
+};
+
+/// Anonymous readers only see published posts; signed-in users also see
+/// their own drafts, and admins everything.
+#[utoipa::path(
+    get,
+    path = "/posts",
+    tag = "posts",
+    params(
+        ListQuery,
+    ),
+    responses(
+        (status = 200, description = "One page of posts", body = Page<BlogPost>),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn get_blog_posts_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    query: Result<axum::extract::Query<ListQuery>, QueryRejection>,
+) -> Result<axum::Json<Page<BlogPost>>, ApiError> {
+    let axum::extract::Query(query) = query?;
+    let page = list_blog_posts(pool, &query, viewer.as_ref()).await?;
+    Ok(axum::Json(page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "posts",
+    params(
+        SearchQuery,
+    ),
+    responses(
+        (status = 200, description = "The best matches first", body = Vec<SearchHit>),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn search_blog_posts_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    query: Result<axum::extract::Query<SearchQuery>, QueryRejection>,
+) -> Result<axum::Json<Vec<SearchHit>>, ApiError> {
+    let axum::extract::Query(query) = query?;
+    let hits = search_blog_posts(pool, &query, viewer.as_ref()).await?;
+    Ok(axum::Json(hits))
+}
+
+/// A post in the response body, with its version in the `ETag` header.
+pub fn post_response(post: BlogPost) -> impl IntoResponse {
+    ([(ETAG, etag(&post))], axum::Json(post))
+}
+
+/// Whether the `Accept` header asks for HTML ahead of JSON. Browsers list
+/// `text/html` first; API clients send `application/json` or `*/*`.
+fn prefers_html(headers: &HeaderMap) -> bool {
+    let accept = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    match (accept.find("text/html"), accept.find("application/json")) {
+        (Some(html), Some(json)) => html < json,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Loads a post, as long as `viewer` may see it. Unpublished posts are
+/// reported as missing to everyone else.
+pub async fn get_visible_post(
+    pool: sqlx::SqlitePool,
+    viewer: Option<&User>,
+    id: i32,
+) -> Result<BlogPost, ApiError> {
+    let post = get_blog_post(pool, id).await?;
+    if !post.is_visible_to(viewer) {
+        return Err(ApiError::NotFound);
+    }
+    Ok(post)
+}
+
+/// The post as JSON, or with `?format=html` (or an `Accept` header that
+/// prefers it) just its body rendered to sanitized HTML.
+#[utoipa::path(
+    get,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        PostQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The post, or its body as HTML",
+            content((BlogPost = "application/json"), (String = "text/html")),
+            headers(("etag" = String, description = "The post's version"))
+        ),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn get_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    headers: HeaderMap,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    query: Result<axum::extract::Query<PostQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Query(query) = query?;
+    let format = match query.format {
+        Some(format) => format,
+        None if prefers_html(&headers) => PostFormat::Html,
+        None => PostFormat::Json,
+    };
+    let post = get_visible_post(pool.clone(), viewer.as_ref(), id).await?;
+    let vary = [(VARY, HeaderValue::from_static("accept"))];
+    match format {
+        PostFormat::Json => Ok((vary, post_response(post)).into_response()),
+        PostFormat::Html => {
+            let html = get_rendered_body(pool, &post).await?;
+            Ok((vary, axum::response::Html(html)).into_response())
+        }
+    }
+}
+
+/// Responds `201 Created` with the new id, and its URL in `Location`.
+#[utoipa::path(
+    post,
+    path = "/posts",
+    tag = "posts",
+    request_body = CreatePost,
+    responses(
+        (
+            status = 201,
+            description = "The id of the new post",
+            body = i32,
+            headers(("location" = String, description = "URL of the new post"))
+        ),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "Only admins may post as someone else", body = ErrorBody),
+        (status = 422, description = "Some fields are invalid", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn add_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    post: Result<axum::extract::Json<CreatePost>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Json(post) = post?;
+    let post = post.validate()?;
+    // Posts belong to whoever created them, unless an admin says otherwise
+    let author = match post.author {
+        Some(author) if !user.can_edit(&author) => return Err(ApiError::Forbidden),
+        Some(author) => author,
+        None => user.name,
+    };
+    let publication = Publication::resolve(None, post.status, post.publish_at, Utc::now())?;
+    let id = add_blog_post(pool, post.title, post.body, author, publication, &post.tags).await?;
+    Ok((
+        StatusCode::CREATED,
+        [(LOCATION, format!("/posts/{id}"))],
+        axum::Json(id),
+    ))
+}
+
+/// Loads a post and checks that `user` may change it. Someone else's
+/// unpublished post is a 404, as it is when read, so that its existence
+/// isn't given away.
+pub async fn get_editable_post(
+    pool: sqlx::SqlitePool,
+    user: &User,
+    id: i32,
+) -> Result<BlogPost, ApiError> {
+    let post = get_blog_post(pool, id).await?;
+    if !post.is_visible_to(Some(user)) {
+        return Err(ApiError::NotFound);
+    }
+    if !user.can_edit(&post.author) {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(post)
+}
+
+/// PUT: replaces the title and body (and the status and tags, if given), and
+/// returns the updated post. Honours
+/// `If-Match`, answering 412 if the post has moved on.
+#[utoipa::path(
+    put,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being changed"),
+    ),
+    request_body = UpdatePost,
+    responses(
+        (status = 200, description = "The post", body = BlogPost, headers(("etag" = String, description = "The post's version"))),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+        (status = 412, description = "`If-Match` names an old version of the post", body = ErrorBody),
+        (status = 422, description = "Some fields are invalid", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    if_match: IfMatch,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    post: Result<axum::extract::Json<UpdatePost>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Json(post) = post?;
+    let post = post.validate()?;
+    let existing = get_editable_post(pool.clone(), &user, id).await?;
+    let version = if_match.check(&existing)?;
+    let publication = Publication::resolve(
+        Some(existing.publication()),
+        post.status,
+        post.publish_at,
+        Utc::now(),
+    )?;
+    update_blog_post(pool.clone(), &post.merge(&existing, publication), version).await?;
+    Ok(post_response(get_blog_post(pool, id).await?))
+}
+
+/// PATCH: changes only the fields supplied, and returns the updated post.
+/// Even without `If-Match` the write is conditional on the version the patch
+/// was merged onto, so a concurrent edit is never silently overwritten; the
+/// caller gets a 409 and can retry.
+#[utoipa::path(
+    patch,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being changed"),
+    ),
+    request_body = PatchPost,
+    responses(
+        (status = 200, description = "The post", body = BlogPost, headers(("etag" = String, description = "The post's version"))),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+        (status = 409, description = "The post changed while it was being patched", body = ErrorBody),
+        (status = 412, description = "`If-Match` names an old version of the post", body = ErrorBody),
+        (status = 422, description = "Some fields are invalid", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn patch_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    if_match: IfMatch,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    patch: Result<axum::extract::Json<PatchPost>, JsonRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Json(patch) = patch?;
+    let patch = patch.validate()?;
+    let existing = get_editable_post(pool.clone(), &user, id).await?;
+    let precondition = if_match.check(&existing)?;
+    let post = patch.apply(&existing);
+    let publication = Publication::resolve(
+        Some(existing.publication()),
+        post.status,
+        post.publish_at,
+        Utc::now(),
+    )?;
+    let version = Some(existing.version);
+    update_blog_post(pool.clone(), &post.merge(&existing, publication), version)
+        .await
+        .map_err(|err| match (ApiError::from(err), precondition) {
+            (ApiError::PreconditionFailed, None) => {
+                ApiError::Conflict("The post changed while it was being patched".to_string())
+            }
+            (err, _) => err,
+        })?;
+    Ok(post_response(get_blog_post(pool, id).await?))
+}
+
+/// Honours `If-Match` in the same way as the update handlers.
+#[utoipa::path(
+    delete,
+    path = "/posts/{id}",
+    tag = "posts",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being changed"),
+    ),
+    responses(
+        (status = 204, description = "The post was moved to the trash"),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+        (status = 412, description = "`If-Match` names an old version of the post", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn delete_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    if_match: IfMatch,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+) -> Result<StatusCode, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let existing = get_editable_post(pool.clone(), &user, id).await?;
+    let version = if_match.check(&existing)?;
+    delete_blog_post(pool, id, version).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
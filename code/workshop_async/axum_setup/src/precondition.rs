@@ -0,0 +1,62 @@
+use crate::error::ApiError;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::IF_MATCH, request::Parts, HeaderValue},
+};
+use blog_core::BlogPost;
+
+/// The entity tag for a post: its version, as a strong ETag.
+pub fn etag(post: &BlogPost) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", post.version)).expect("an integer is a valid header")
+}
+
+/// The `If-Match` request header. Absent or `*` means "any version";
+/// otherwise the write only goes ahead if the post's current ETag is listed.
+pub struct IfMatch(Option<Vec<String>>);
+
+impl IfMatch {
+    /// Checks the current post against the header, returning the version to
+    /// make the write conditional on (so a concurrent change between this
+    /// check and the write is still caught).
+    pub fn check(&self, current: &BlogPost) -> Result<Option<i32>, ApiError> {
+        let Some(tags) = &self.0 else {
+            return Ok(None);
+        };
+        let current_tag = etag(current);
+        if tags.iter().any(|tag| tag.as_bytes() == current_tag.as_bytes()) {
+            Ok(Some(current.version))
+        } else {
+            Err(ApiError::PreconditionFailed)
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let mut tags = Vec::new();
+        for value in parts.headers.get_all(IF_MATCH) {
+            let value = value
+                .to_str()
+                .map_err(|_| ApiError::PreconditionFailed)?;
+            for tag in value.split(',').map(str::trim) {
+                if tag == "*" {
+                    return Ok(IfMatch(None));
+                }
+                // Weak tags never match under If-Match's strong comparison
+                tags.push(tag.to_string());
+            }
+        }
+        if tags.is_empty() {
+            Ok(IfMatch(None))
+        } else {
+            Ok(IfMatch(Some(tags)))
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::auth::{AuthUser, MaybeUser};
+use crate::error::{ApiError, ErrorBody};
+use crate::posts::{get_editable_post, post_response};
+use crate::precondition::IfMatch;
+use axum::extract::rejection::{PathRejection, QueryRejection};
+use axum::response::IntoResponse;
+use axum::Extension;
+use blog_core::db::{
+    get_blog_post, get_deleted_blog_post, get_revision, list_revisions, update_blog_post,
+};
+use blog_core::{BlogPost, DiffQuery, PostRevision, RevisionDiff, User};
+use similar::TextDiff;
+
+/// The history of an unpublished post is as private as the post itself.
+/// Once a post is in the trash, or gone, only its author and admins may see
+/// its history; for a post that is gone, the author is taken from its last
+/// revision.
+async fn check_history_visible(
+    pool: sqlx::SqlitePool,
+    viewer: Option<&User>,
+    id: i32,
+) -> Result<(), ApiError> {
+    match get_blog_post(pool.clone(), id).await.map_err(ApiError::from) {
+        Ok(post) if post.is_visible_to(viewer) => return Ok(()),
+        Ok(_) => return Err(ApiError::NotFound),
+        Err(ApiError::NotFound) => {}
+        Err(err) => return Err(err),
+    }
+    let author = match get_deleted_blog_post(pool.clone(), id).await.map_err(ApiError::from) {
+        Ok(post) => Some(post.author),
+        Err(ApiError::NotFound) => list_revisions(pool, id).await?.pop().map(|last| last.author),
+        Err(err) => return Err(err),
+    };
+    match (viewer, author) {
+        (Some(user), Some(author)) if user.can_edit(&author) => Ok(()),
+        _ => Err(ApiError::NotFound),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions",
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+    ),
+    responses(
+        (status = 200, description = "Past versions of the post, oldest first", body = Vec<PostRevision>),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn list_revisions_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+) -> Result<axum::Json<Vec<PostRevision>>, ApiError> {
+    let axum::extract::Path(id) = id?;
+    check_history_visible(pool.clone(), viewer.as_ref(), id).await?;
+    let revisions = list_revisions(pool, id).await?;
+    Ok(axum::Json(revisions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/revisions/{version}",
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("version" = i32, Path, description = "Version of the post"),
+    ),
+    responses(
+        (status = 200, description = "The post as it was", body = PostRevision),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post or version", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn get_revision_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    path: Result<axum::extract::Path<(i32, i32)>, PathRejection>,
+) -> Result<axum::Json<PostRevision>, ApiError> {
+    let axum::extract::Path((id, version)) = path?;
+    check_history_visible(pool.clone(), viewer.as_ref(), id).await?;
+    let revision = get_revision(pool, id, version).await?;
+    Ok(axum::Json(revision))
+}
+
+/// Title and body of any version of a post, past or current.
+async fn version_text(
+    pool: sqlx::SqlitePool,
+    id: i32,
+    version: i32,
+) -> Result<(String, String), ApiError> {
+    match get_revision(pool.clone(), id, version).await.map_err(ApiError::from) {
+        Ok(revision) => Ok((revision.title, revision.body)),
+        Err(ApiError::NotFound) => {
+            let post = get_blog_post(pool, id).await?;
+            if post.version == version {
+                Ok((post.title, post.body))
+            } else {
+                Err(ApiError::NotFound)
+            }
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The current version of a post or, once it is in the trash, its last
+/// revision, which is the version it was deleted at.
+async fn latest_version(pool: sqlx::SqlitePool, id: i32) -> Result<i32, ApiError> {
+    match get_blog_post(pool.clone(), id).await.map_err(ApiError::from) {
+        Ok(post) => Ok(post.version),
+        Err(ApiError::NotFound) => list_revisions(pool, id)
+            .await?
+            .pop()
+            .map(|last| last.version)
+            .ok_or(ApiError::NotFound),
+        Err(err) => Err(err),
+    }
+}
+
+fn unified_diff(old: &str, new: &str, from: i32, to: i32) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(&format!("v{from}"), &format!("v{to}"))
+        .to_string()
+}
+
+#[utoipa::path(
+    get,
+    path = "/posts/{id}/diff",
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        DiffQuery,
+    ),
+    responses(
+        (status = 200, description = "Unified diffs between the versions", body = RevisionDiff),
+        (status = 400, description = "The query string is invalid", body = ErrorBody),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+        (status = 404, description = "No such post or version", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn diff_revisions_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+    query: Result<axum::extract::Query<DiffQuery>, QueryRejection>,
+) -> Result<axum::Json<RevisionDiff>, ApiError> {
+    let axum::extract::Path(id) = id?;
+    let axum::extract::Query(query) = query?;
+    check_history_visible(pool.clone(), viewer.as_ref(), id).await?;
+    let to = match query.to {
+        Some(to) => to,
+        None => latest_version(pool.clone(), id).await?,
+    };
+    let (old_title, old_body) = version_text(pool.clone(), id, query.from).await?;
+    let (new_title, new_body) = version_text(pool, id, to).await?;
+    Ok(axum::Json(RevisionDiff {
+        post_id: id,
+        from: query.from,
+        to,
+        title: unified_diff(&old_title, &new_title, query.from, to),
+        body: unified_diff(&old_body, &new_body, query.from, to),
+    }))
+}
+
+/// Puts an old version's title and body back as a new version of the post.
+/// Honours `If-Match` like any other update.
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/revisions/{version}/restore",
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+        ("version" = i32, Path, description = "Version of the post"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version being changed"),
+    ),
+    responses(
+        (status = 200, description = "The post", body = BlogPost, headers(("etag" = String, description = "The post's version"))),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post or version", body = ErrorBody),
+        (status = 412, description = "`If-Match` names an old version of the post", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn restore_revision_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    if_match: IfMatch,
+    path: Result<axum::extract::Path<(i32, i32)>, PathRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Path((id, version)) = path?;
+    let existing = get_editable_post(pool.clone(), &user, id).await?;
+    let expected = if_match.check(&existing)?;
+    let revision = get_revision(pool.clone(), id, version).await?;
+    let restored = BlogPost {
+        title: revision.title,
+        body: revision.body,
+        ..existing
+    };
+    update_blog_post(pool.clone(), &restored, expected).await?;
+    Ok(post_response(get_blog_post(pool, id).await?))
+}
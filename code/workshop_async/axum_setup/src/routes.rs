@@ -0,0 +1,65 @@
+use crate::comments::{
+    add_comment_handler, delete_comment_handler, get_comment_handler, list_comments_handler,
+    moderate_comment_handler,
+};
+use crate::feeds::{atom_feed_handler, rss_feed_handler};
+use crate::posts::{
+    add_blog_post_handler, delete_blog_post_handler, get_blog_post_handler,
+    get_blog_posts_handler, patch_blog_post_handler, search_blog_posts_handler,
+    update_blog_post_handler,
+};
+use crate::revisions::{
+    diff_revisions_handler, get_revision_handler, list_revisions_handler,
+    restore_revision_handler,
+};
+use crate::tags::list_tags_handler;
+use crate::trash::{list_trash_handler, purge_blog_post_handler, undelete_blog_post_handler};
+use axum::routing::{delete, get, post};
+use axum::Router;
+
+/// The documented API. Every route here is described in `openapi::ApiDoc`,
+/// and the tests there check that the two agree by sending requests to
+/// the app.
+pub fn api_routes() -> Router {
+    Router::new()
+        .route("/search", get(search_blog_posts_handler))
+        .route("/tags", get(list_tags_handler))
+        .route(
+            "/posts",
+            get(get_blog_posts_handler).post(add_blog_post_handler),
+        )
+        .route(
+            "/posts/:id",
+            get(get_blog_post_handler)
+                .put(update_blog_post_handler)
+                .patch(patch_blog_post_handler)
+                .delete(delete_blog_post_handler),
+        )
+        .route("/posts/:id/revisions", get(list_revisions_handler))
+        .route("/posts/:id/revisions/:version", get(get_revision_handler))
+        .route(
+            "/posts/:id/revisions/:version/restore",
+            post(restore_revision_handler),
+        )
+        .route("/posts/:id/diff", get(diff_revisions_handler))
+        .route("/posts/:id/undelete", post(undelete_blog_post_handler))
+        .route(
+            "/posts/:id/comments",
+            get(list_comments_handler).post(add_comment_handler),
+        )
+        .route(
+            "/posts/:id/comments/:comment_id",
+            get(get_comment_handler)
+                .patch(moderate_comment_handler)
+                .delete(delete_comment_handler),
+        )
+        .route("/trash", get(list_trash_handler))
+        .route("/trash/:id", delete(purge_blog_post_handler))
+}
+
+/// The RSS and Atom feeds, which can be turned off on their own.
+pub fn feed_routes() -> Router {
+    Router::new()
+        .route("/feed.rss", get(rss_feed_handler))
+        .route("/feed.atom", get(atom_feed_handler))
+}
@@ -0,0 +1,24 @@
+use blog_core::db::publish_scheduled_posts;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Publishes scheduled posts whose time has come, once every `interval`,
+/// until `shutdown` is cancelled. A run that has started is finished first.
+pub async fn publish_scheduled(
+    pool: sqlx::SqlitePool,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.cancelled() => break,
+        }
+        match publish_scheduled_posts(pool.clone(), chrono::Utc::now()).await {
+            Ok(0) => {}
+            Ok(published) => tracing::info!(published, "published scheduled posts"),
+            Err(err) => tracing::error!("publishing scheduled posts failed: {err:#}"),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use axum::Router;
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Serves `app` on `listener` until `signal` resolves, then stops taking
+/// connections and cancels `shutdown` for the `background` tasks.
+///
+/// Requests in flight, the background tasks and closing `pool` share
+/// `drain_timeout` between them. Whatever is still running after that is
+/// left behind rather than waited for, so a stuck request can't keep the
+/// process alive.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    pool: sqlx::SqlitePool,
+    background: Vec<JoinHandle<()>>,
+    shutdown: CancellationToken,
+    drain_timeout: Duration,
+    signal: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned());
+    let mut server = tokio::spawn(async move { server.await });
+    let stopped = tokio::select! {
+        result = &mut server => Some(result),
+        result = signal => {
+            result?;
+            None
+        }
+    };
+
+    tracing::info!(?drain_timeout, "shutting down, letting requests finish");
+    shutdown.cancel();
+    let deadline = Instant::now() + drain_timeout;
+    let stopped = match stopped {
+        Some(result) => Some(result),
+        None => timeout_at(deadline, &mut server).await.ok(),
+    };
+    match stopped {
+        Some(result) => result??,
+        None => {
+            // Only stops the accept loop: each connection runs in a task of
+            // its own, and may still be holding a database connection
+            tracing::warn!("requests were still running after {drain_timeout:?}; dropping them");
+            server.abort();
+        }
+    }
+
+    // The background tasks stop between runs, and then the pool can be
+    // closed, checkpointing the WAL into the database file
+    let closed = timeout_at(deadline, async {
+        for task in background {
+            task.await?;
+        }
+        pool.close().await;
+        anyhow::Ok(())
+    });
+    match closed.await {
+        Ok(result) => result?,
+        Err(_) => tracing::warn!("the database was still in use after {drain_timeout:?}"),
+    }
+    tracing::info!("shut down");
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::auth::MaybeUser;
+use crate::error::{ApiError, ErrorBody};
+use axum::Extension;
+use blog_core::db::list_tags;
+use blog_core::TagCount;
+
+/// Counts only take in the posts the caller may see.
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "Every tag in use, with how many posts have it", body = Vec<TagCount>),
+        (status = 401, description = "The bearer token is invalid", body = ErrorBody),
+    ),
+    security((), ("bearer" = []))
+)]
+pub async fn list_tags_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    MaybeUser(viewer): MaybeUser,
+) -> Result<axum::Json<Vec<TagCount>>, ApiError> {
+    let tags = list_tags(pool, viewer.as_ref()).await?;
+    Ok(axum::Json(tags))
+}
@@ -0,0 +1,70 @@
+use crate::config::{LogConfig, LogFormat};
+use crate::monitoring::{QueryTimer, QUERY_TARGET};
+use axum::body::Body;
+use axum::http::{HeaderName, Request, Response};
+use axum::Router;
+use std::io::IsTerminal;
+use std::time::Duration;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::{field, Level, Span};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Identifies a request in the logs. Taken from the caller if they sent
+/// one, so that a client can tie its own failures to ours, and sent back
+/// in the response either way.
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Writes log events to stdout, at the configured level unless `RUST_LOG`
+/// says otherwise. Query spans are timed for the metrics whatever the level.
+pub fn init_logging(config: &LogConfig) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(config.level.as_str()));
+    let output = match config.format {
+        LogFormat::Text => fmt::layer()
+            .with_ansi(std::io::stdout().is_terminal())
+            .boxed(),
+        LogFormat::Json => fmt::layer().json().boxed(),
+    };
+    let queries = Targets::new().with_target(QUERY_TARGET, Level::DEBUG);
+    tracing_subscriber::registry()
+        .with(output.with_filter(filter))
+        .with(QueryTimer.with_filter(queries))
+        .init();
+}
+
+/// Runs each request in a span carrying its method, path and id, and logs
+/// its status and latency once the response is ready.
+pub fn trace_requests(app: Router) -> Router {
+    app.layer(
+        TraceLayer::new_for_http()
+            .make_span_with(request_span)
+            .on_response(record_response),
+    )
+    .layer(PropagateRequestIdLayer::new(REQUEST_ID))
+    .layer(SetRequestIdLayer::new(REQUEST_ID, MakeRequestUuid))
+}
+
+fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+        status = field::Empty,
+        latency = field::Empty,
+    )
+}
+
+fn record_response(response: &Response<Body>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency", field::debug(latency));
+    tracing::info!("finished");
+}
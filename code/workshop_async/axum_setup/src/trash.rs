@@ -0,0 +1,124 @@
+use crate::auth::AuthUser;
+use crate::error::{ApiError, ErrorBody};
+use crate::posts::post_response;
+use axum::extract::rejection::PathRejection;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+use blog_core::db::{
+    get_blog_post, get_deleted_blog_post, list_deleted_blog_posts, purge_blog_post,
+    purge_deleted_blog_posts, undelete_blog_post,
+};
+use blog_core::{BlogPost, User};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Admins see the whole trash; everyone else only their own posts.
+#[utoipa::path(
+    get,
+    path = "/trash",
+    tag = "trash",
+    responses(
+        (status = 200, description = "Deleted posts the caller may restore", body = Vec<BlogPost>),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn list_trash_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+) -> Result<axum::Json<Vec<BlogPost>>, ApiError> {
+    let author = (!user.is_admin).then_some(user.name.as_str());
+    let posts = list_deleted_blog_posts(pool, author).await?;
+    Ok(axum::Json(posts))
+}
+
+/// Loads a post from the trash and checks that `user` may change it.
+async fn get_editable_deleted_post(
+    pool: sqlx::SqlitePool,
+    user: &User,
+    id: i32,
+) -> Result<BlogPost, ApiError> {
+    let post = get_deleted_blog_post(pool, id).await?;
+    if !user.can_edit(&post.author) {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(post)
+}
+
+/// Puts a post back, returning it as it was before it was deleted.
+#[utoipa::path(
+    post,
+    path = "/posts/{id}/undelete",
+    tag = "trash",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+    ),
+    responses(
+        (status = 200, description = "The post", body = BlogPost, headers(("etag" = String, description = "The post's version"))),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post in the trash", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn undelete_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let axum::extract::Path(id) = id?;
+    get_editable_deleted_post(pool.clone(), &user, id).await?;
+    undelete_blog_post(pool.clone(), id).await?;
+    Ok(post_response(get_blog_post(pool, id).await?))
+}
+
+/// Empties one post out of the trash now, rather than waiting for the purge.
+#[utoipa::path(
+    delete,
+    path = "/trash/{id}",
+    tag = "trash",
+    params(
+        ("id" = i32, Path, description = "Post id"),
+    ),
+    responses(
+        (status = 204, description = "The post was deleted for good"),
+        (status = 401, description = "The bearer token is missing or invalid", body = ErrorBody),
+        (status = 403, description = "The caller may not change this post", body = ErrorBody),
+        (status = 404, description = "No such post in the trash", body = ErrorBody),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn purge_blog_post_handler(
+    Extension(pool): Extension<sqlx::SqlitePool>,
+    AuthUser(user): AuthUser,
+    id: Result<axum::extract::Path<i32>, PathRejection>,
+) -> Result<StatusCode, ApiError> {
+    let axum::extract::Path(id) = id?;
+    get_editable_deleted_post(pool.clone(), &user, id).await?;
+    purge_blog_post(pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Purges posts that have been in the trash for longer than `retention`,
+/// once every `interval`, until `shutdown` is cancelled.
+pub async fn purge_trash(
+    pool: sqlx::SqlitePool,
+    retention: chrono::Duration,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.cancelled() => break,
+        }
+        let cutoff = chrono::Utc::now() - retention;
+        match purge_deleted_blog_posts(pool.clone(), cutoff).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!(purged, "purged posts from the trash"),
+            Err(err) => tracing::error!("trash purge failed: {err:#}"),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn signed_in_comments_are_approved() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let response = app
+        .post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Call me later." }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    let comment = response.json();
+    assert_eq!(comment["author"], "alice");
+    assert_eq!(comment["status"], "approved");
+    let location = format!("/posts/2/comments/{}", comment["id"]);
+    assert_eq!(response.header("location"), Some(location.as_str()));
+
+    let listed = app.get("/posts/2/comments").send().await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.json(), json!([comment]));
+    let fetched = app.get(&location).send().await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.json(), comment);
+}
+
+#[tokio::test]
+async fn anonymous_comments_wait_for_moderation() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    let response = app
+        .post("/posts/2/comments")
+        .json(json!({ "body": "First!", "author": "Ahab" }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+    let comment = response.json();
+    assert_eq!(comment["status"], "pending");
+    let uri = format!("/posts/2/comments/{}", comment["id"]);
+
+    assert_eq!(app.get("/posts/2/comments").send().await.json(), json!([]));
+    app.get(&uri)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    let pending = app
+        .get("/posts/2/comments?status=pending")
+        .token(&admin)
+        .send()
+        .await
+        .json();
+    assert_eq!(pending, json!([comment]));
+}
+
+#[tokio::test]
+async fn anonymous_comments_need_a_free_name() {
+    let app = TestApp::new().await;
+    app.user("alice", false).await;
+    app.post("/posts/2/comments")
+        .json(json!({ "body": "Who am I?" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    app.post("/posts/2/comments")
+        .json(json!({ "body": "It's me", "author": "alice" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    app.post("/posts/2/comments")
+        .json(json!({ "body": "It's me too", "author": "ALICE" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+}
+
+#[tokio::test]
+async fn comments_need_a_visible_post() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let comment = json!({ "body": "Hello" });
+    app.post("/posts/99/comments")
+        .token(&alice)
+        .json(comment.clone())
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.get("/posts/99/comments")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": " " }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+}
+
+#[tokio::test]
+async fn replies_must_be_to_a_comment_on_the_same_post() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let parent = app
+        .post("/posts/1/comments")
+        .token(&alice)
+        .json(json!({ "body": "On the first post" }))
+        .send()
+        .await
+        .json();
+    app.post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Reply", "parent_id": parent["id"] }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    let reply = app
+        .post("/posts/1/comments")
+        .token(&alice)
+        .json(json!({ "body": "Reply", "parent_id": parent["id"] }))
+        .send()
+        .await;
+    assert_eq!(reply.status, StatusCode::CREATED, "{}", reply.text());
+    assert_eq!(reply.json()["parent_id"], parent["id"]);
+}
+
+#[tokio::test]
+async fn moderators_change_a_comments_status() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app.add_post(&alice, json!({ "title": "Title", "body": "Body" })).await;
+    let comment = app
+        .post(&format!("/posts/{id}/comments"))
+        .json(json!({ "body": "Buy now", "author": "Spammer" }))
+        .send()
+        .await
+        .json();
+    let uri = format!("/posts/{id}/comments/{}", comment["id"]);
+    let response = app
+        .patch(&uri)
+        .token(&alice)
+        .json(json!({ "status": "spam" }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(response.json()["status"], "spam");
+}
+
+#[tokio::test]
+async fn only_moderators_change_a_comments_status() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let comment = app
+        .post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Hello" }))
+        .send()
+        .await
+        .json();
+    let uri = format!("/posts/2/comments/{}", comment["id"]);
+    app.patch(&uri)
+        .token(&alice)
+        .json(json!({ "status": "approved" }))
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    app.patch(&uri)
+        .json(json!({ "status": "approved" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+    let admin = app.user("admin", true).await;
+    app.patch(&uri)
+        .token(&admin)
+        .json(json!({ "status": "deleted" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "bad_request");
+}
+
+#[tokio::test]
+async fn commenters_delete_their_comments_and_replies() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let parent = app
+        .post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Parent" }))
+        .send()
+        .await
+        .json();
+    app.post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Child", "parent_id": parent["id"] }))
+        .send()
+        .await;
+    let response = app
+        .delete(&format!("/posts/2/comments/{}", parent["id"]))
+        .token(&alice)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert_eq!(app.get("/posts/2/comments").send().await.json(), json!([]));
+}
+
+#[tokio::test]
+async fn only_commenters_and_moderators_delete_comments() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let bob = app.user("bob", false).await;
+    let comment = app
+        .post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "Mine" }))
+        .send()
+        .await
+        .json();
+    app.delete(&format!("/posts/2/comments/{}", comment["id"]))
+        .token(&bob)
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    app.delete("/posts/2/comments/99")
+        .token(&alice)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
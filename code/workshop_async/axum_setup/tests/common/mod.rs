@@ -0,0 +1,149 @@
+//! Drives the router in-process against a fresh in-memory database, so each
+//! test starts from the two posts in the seed migration.
+
+// Each test binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use axum::Router;
+use axum_setup::config::Config;
+use blog_core::db::{add_user, get_connection_pool, issue_token, run_migrations};
+use serde_json::Value;
+use tower::ServiceExt;
+
+pub struct TestApp {
+    pub pool: sqlx::SqlitePool,
+    router: Router,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_config(&Config::default()).await
+    }
+
+    pub async fn with_config(config: &Config) -> Self {
+        // Every `sqlite::memory:` pool gets its own database, shared by the
+        // pool's connections
+        let pool = get_connection_pool("sqlite::memory:").await.unwrap();
+        run_migrations(pool.clone()).await.unwrap();
+        let router = axum_setup::app(pool.clone(), config);
+        TestApp { pool, router }
+    }
+
+    /// Adds a user, returning a bearer token for them.
+    pub async fn user(&self, name: &str, is_admin: bool) -> String {
+        let id = add_user(self.pool.clone(), name.to_string(), is_admin)
+            .await
+            .unwrap();
+        issue_token(self.pool.clone(), id, None).await.unwrap().token
+    }
+
+    pub fn get(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::GET, uri)
+    }
+
+    pub fn post(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::POST, uri)
+    }
+
+    pub fn put(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::PUT, uri)
+    }
+
+    pub fn patch(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::PATCH, uri)
+    }
+
+    pub fn delete(&self, uri: &str) -> TestRequest<'_> {
+        self.request(Method::DELETE, uri)
+    }
+
+    pub fn request(&self, method: Method, uri: &str) -> TestRequest<'_> {
+        TestRequest {
+            app: self,
+            request: Request::builder().method(method).uri(uri),
+            body: Body::empty(),
+        }
+    }
+
+    /// Creates a post as the owner of `token`, returning its id.
+    pub async fn add_post(&self, token: &str, post: Value) -> i32 {
+        let response = self.post("/posts").token(token).json(post).send().await;
+        assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+        response.json().as_i64().unwrap() as i32
+    }
+}
+
+pub struct TestRequest<'a> {
+    app: &'a TestApp,
+    request: axum::http::request::Builder,
+    body: Body,
+}
+
+impl TestRequest<'_> {
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.request = self.request.header(name, value);
+        self
+    }
+
+    pub fn token(self, token: &str) -> Self {
+        self.header("authorization", &format!("Bearer {token}"))
+    }
+
+    pub fn json(mut self, body: Value) -> Self {
+        self.request = self.request.header(header::CONTENT_TYPE, "application/json");
+        self.body = Body::from(body.to_string());
+        self
+    }
+
+    /// A body sent as it is, for requests that aren't valid JSON.
+    pub fn raw(mut self, content_type: &str, body: &str) -> Self {
+        self.request = self.request.header(header::CONTENT_TYPE, content_type);
+        self.body = Body::from(body.to_string());
+        self
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let request = self.request.body(self.body).unwrap();
+        let response = self.app.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        TestResponse {
+            status,
+            headers,
+            body: body.to_vec(),
+        }
+    }
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|err| panic!("{err}: {}", self.text()))
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Checks the status, and that the body is the JSON error with `code`.
+    #[track_caller]
+    pub fn assert_error(&self, status: StatusCode, code: &str) {
+        assert_eq!(self.status, status, "{}", self.text());
+        assert_eq!(self.json()["error"]["code"], code, "{}", self.text());
+    }
+}
@@ -0,0 +1,108 @@
+//! The background jobs' queries, called directly with an explicit time so
+//! that they don't depend on the clock.
+
+mod common;
+
+use blog_core::db::{
+    add_blog_post, add_comment, delete_blog_post, get_blog_post, get_deleted_blog_post,
+    publish_scheduled_posts, purge_deleted_blog_posts,
+};
+use blog_core::{CommentStatus, PostStatus, Publication};
+use chrono::{TimeZone, Utc};
+use common::TestApp;
+
+/// Counts the rows in `table` that belong to post `id`.
+async fn rows_for(app: &TestApp, table: &str, id: i32) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE post_id = ?"))
+        .bind(id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+/// A post with a tag and a comment, put in the trash.
+async fn trashed_post(app: &TestApp, title: &str) -> i32 {
+    let id = add_blog_post(
+        app.pool.clone(),
+        title.to_string(),
+        "Body".to_string(),
+        "alice".to_string(),
+        Publication::default(),
+        &["rust".to_string()],
+    )
+    .await
+    .unwrap();
+    add_comment(
+        app.pool.clone(),
+        id,
+        None,
+        "bob".to_string(),
+        "Nice".to_string(),
+        CommentStatus::Approved,
+    )
+    .await
+    .unwrap();
+    delete_blog_post(app.pool.clone(), id, None).await.unwrap();
+    id
+}
+
+#[tokio::test]
+async fn purges_only_posts_trashed_before_the_cutoff() {
+    let app = TestApp::new().await;
+    let old = trashed_post(&app, "Old").await;
+    let recent = trashed_post(&app, "Recent").await;
+    let trashed_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    sqlx::query("UPDATE blog_posts SET deleted_at = ? WHERE id = ?")
+        .bind(trashed_at)
+        .bind(old)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let cutoff = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+    let purged = purge_deleted_blog_posts(app.pool.clone(), cutoff).await.unwrap();
+    assert_eq!(purged, 1);
+    assert!(get_deleted_blog_post(app.pool.clone(), old).await.is_err());
+    for table in ["comments", "post_tags", "blog_post_revisions"] {
+        assert_eq!(rows_for(&app, table, old).await, 0, "{table}");
+        assert_eq!(rows_for(&app, table, recent).await, 1, "{table}");
+    }
+    // The newer post is still in the trash, and live posts are untouched
+    let kept = get_deleted_blog_post(app.pool.clone(), recent).await.unwrap();
+    assert_eq!(kept.title, "Recent");
+    assert!(get_blog_post(app.pool.clone(), 1).await.is_ok());
+}
+
+#[tokio::test]
+async fn publishes_scheduled_posts_that_are_due() {
+    let app = TestApp::new().await;
+    let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+    let scheduled = |hour| Publication {
+        status: PostStatus::Scheduled,
+        publish_at: Some(Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap()),
+    };
+    let mut ids = Vec::new();
+    for (title, hour) in [("Due", 8), ("Later", 10)] {
+        let id = add_blog_post(
+            app.pool.clone(),
+            title.to_string(),
+            "Body".to_string(),
+            "alice".to_string(),
+            scheduled(hour),
+            &[],
+        )
+        .await
+        .unwrap();
+        ids.push(id);
+    }
+
+    assert_eq!(publish_scheduled_posts(app.pool.clone(), now).await.unwrap(), 1);
+    let due = get_blog_post(app.pool.clone(), ids[0]).await.unwrap();
+    assert_eq!(due.status, PostStatus::Published);
+    assert_eq!(due.publish_at, scheduled(8).publish_at);
+    assert_eq!(due.version, 1);
+    let later = get_blog_post(app.pool.clone(), ids[1]).await.unwrap();
+    assert_eq!(later.status, PostStatus::Scheduled);
+    // Nothing more is due until the later post's time comes
+    assert_eq!(publish_scheduled_posts(app.pool.clone(), now).await.unwrap(), 0);
+}
@@ -0,0 +1,312 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+/// A post by alice at version 2, with version 1 in its history.
+async fn edited_post(app: &TestApp, alice: &str) -> i32 {
+    let id = app
+        .add_post(alice, json!({ "title": "Draft title", "body": "one\ntwo\n" }))
+        .await;
+    let response = app
+        .put(&format!("/posts/{id}"))
+        .token(alice)
+        .json(json!({ "title": "Final title", "body": "one\nthree\n" }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    id
+}
+
+#[tokio::test]
+async fn lists_and_gets_revisions() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    let revisions = app.get(&format!("/posts/{id}/revisions")).send().await;
+    assert_eq!(revisions.status, StatusCode::OK);
+    let revisions = revisions.json();
+    assert_eq!(revisions.as_array().unwrap().len(), 1);
+    assert_eq!(revisions[0]["version"], 1);
+    assert_eq!(revisions[0]["operation"], "update");
+    let revision = app.get(&format!("/posts/{id}/revisions/1")).send().await;
+    assert_eq!(revision.status, StatusCode::OK);
+    assert_eq!(revision.json()["title"], "Draft title");
+}
+
+#[tokio::test]
+async fn reports_missing_revisions() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    app.get(&format!("/posts/{id}/revisions/5"))
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.get("/posts/99/revisions")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn hides_the_history_of_drafts() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    app.patch(&format!("/posts/{id}"))
+        .token(&alice)
+        .json(json!({ "status": "draft" }))
+        .send()
+        .await;
+    let uri = format!("/posts/{id}/revisions");
+    app.get(&uri)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    assert_eq!(app.get(&uri).token(&alice).send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn hides_the_history_of_trashed_posts() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let bob = app.user("bob", false).await;
+    let admin = app.user("admin", true).await;
+    let id = edited_post(&app, &alice).await;
+    app.patch(&format!("/posts/{id}"))
+        .token(&alice)
+        .json(json!({ "status": "draft" }))
+        .send()
+        .await;
+    app.delete(&format!("/posts/{id}")).token(&alice).send().await;
+    for uri in [
+        format!("/posts/{id}/revisions"),
+        format!("/posts/{id}/revisions/1"),
+        format!("/posts/{id}/diff?from=1"),
+    ] {
+        app.get(&uri)
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "not_found");
+        app.get(&uri)
+            .token(&bob)
+            .send()
+            .await
+            .assert_error(StatusCode::NOT_FOUND, "not_found");
+    }
+    let uri = format!("/posts/{id}/revisions");
+    assert_eq!(app.get(&uri).token(&alice).send().await.status, StatusCode::OK);
+    assert_eq!(app.get(&uri).token(&admin).send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn diffs_against_the_current_version() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    let response = app.get(&format!("/posts/{id}/diff?from=1")).send().await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let diff = response.json();
+    assert_eq!(diff["from"], 1);
+    assert_eq!(diff["to"], 2);
+    let body = diff["body"].as_str().unwrap();
+    assert!(body.contains("-two\n") && body.contains("+three\n"), "{body}");
+}
+
+#[tokio::test]
+async fn diffs_a_trashed_post_against_its_last_version() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    app.delete(&format!("/posts/{id}")).token(&alice).send().await;
+    let response = app
+        .get(&format!("/posts/{id}/diff?from=1"))
+        .token(&alice)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let diff = response.json();
+    assert_eq!(diff["to"], 2);
+    assert!(diff["body"].as_str().unwrap().contains("+three"));
+}
+
+#[tokio::test]
+async fn diff_needs_known_versions() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    app.get(&format!("/posts/{id}/diff"))
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+    app.get(&format!("/posts/{id}/diff?from=1&to=9"))
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn restores_an_old_version() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = edited_post(&app, &alice).await;
+    let response = app
+        .post(&format!("/posts/{id}/revisions/1/restore"))
+        .token(&alice)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let post = response.json();
+    assert_eq!(post["title"], "Draft title");
+    assert_eq!(post["version"], 3);
+}
+
+#[tokio::test]
+async fn only_editors_restore_versions() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let bob = app.user("bob", false).await;
+    let id = edited_post(&app, &alice).await;
+    app.post(&format!("/posts/{id}/revisions/1/restore"))
+        .token(&bob)
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    app.post(&format!("/posts/{id}/revisions/1/restore"))
+        .token(&alice)
+        .header("if-match", "\"1\"")
+        .send()
+        .await
+        .assert_error(StatusCode::PRECONDITION_FAILED, "precondition_failed");
+}
+
+#[tokio::test]
+async fn lists_each_users_own_trash() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let admin = app.user("admin", true).await;
+    let id = app.add_post(&alice, json!({ "title": "Title", "body": "Body" })).await;
+    app.delete(&format!("/posts/{id}")).token(&alice).send().await;
+    app.delete("/posts/1").token(&admin).send().await;
+
+    let mine = app.get("/trash").token(&alice).send().await;
+    assert_eq!(mine.status, StatusCode::OK);
+    let mine = mine.json();
+    assert_eq!(mine.as_array().unwrap().len(), 1);
+    assert_eq!(mine[0]["id"], id);
+    assert!(mine[0]["deleted_at"].is_string());
+    let everything = app.get("/trash").token(&admin).send().await.json();
+    assert_eq!(everything.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn the_trash_needs_a_token() {
+    let app = TestApp::new().await;
+    app.get("/trash")
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[tokio::test]
+async fn undeletes_a_post() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    app.delete("/posts/2").token(&admin).send().await;
+    let response = app.post("/posts/2/undelete").token(&admin).send().await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(response.json()["title"], "Moby Dick");
+    assert_eq!(app.get("/posts/2").send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn only_deleted_posts_can_be_undeleted() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let admin = app.user("admin", true).await;
+    app.post("/posts/2/undelete")
+        .token(&admin)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.delete("/posts/2").token(&admin).send().await;
+    app.post("/posts/2/undelete")
+        .token(&alice)
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+}
+
+#[tokio::test]
+async fn purges_a_post_for_good() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    app.delete("/posts/2").token(&admin).send().await;
+    let response = app.delete("/trash/2").token(&admin).send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    app.post("/posts/2/undelete")
+        .token(&admin)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    // Its history goes with it
+    app.get("/posts/2/revisions")
+        .token(&admin)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn records_the_last_version_when_trashed() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    app.delete("/posts/2").token(&admin).send().await;
+    let revisions = app.get("/posts/2/revisions").token(&admin).send().await.json();
+    assert_eq!(revisions.as_array().unwrap().len(), 1);
+    assert_eq!(revisions[0]["operation"], "delete");
+    assert_eq!(revisions[0]["version"], 1);
+    assert_eq!(revisions[0]["title"], "Moby Dick");
+    // Back out of the trash, that version is current again, and can be
+    // edited and trashed once more
+    app.post("/posts/2/undelete").token(&admin).send().await;
+    let revisions = app.get("/posts/2/revisions").send().await.json();
+    assert_eq!(revisions, json!([]));
+    app.patch("/posts/2")
+        .token(&admin)
+        .json(json!({ "title": "Moby-Dick" }))
+        .send()
+        .await;
+    app.delete("/posts/2").token(&admin).send().await;
+    let revisions = app.get("/posts/2/revisions").token(&admin).send().await.json();
+    let operations: Vec<_> = revisions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|revision| (revision["version"].clone(), revision["operation"].clone()))
+        .collect();
+    assert_eq!(
+        operations,
+        [(json!(1), json!("update")), (json!(2), json!("delete"))]
+    );
+}
+
+#[tokio::test]
+async fn only_trashed_posts_can_be_purged() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let admin = app.user("admin", true).await;
+    app.delete("/trash/2")
+        .token(&admin)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.delete("/posts/2").token(&admin).send().await;
+    app.delete("/trash/2")
+        .token(&alice)
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+}
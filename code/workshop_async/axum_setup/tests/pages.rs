@@ -0,0 +1,363 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_setup::config::Config;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn says_hello() {
+    let app = TestApp::new().await;
+    let response = app.get("/hello").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text(), "Hello, World!");
+}
+
+#[tokio::test]
+async fn tags_responses_with_a_request_id() {
+    let app = TestApp::new().await;
+    let generated = app.get("/posts/99").send().await;
+    assert_eq!(generated.header("x-request-id").map(str::len), Some(36));
+    let echoed = app.get("/hello").header("x-request-id", "abc-123").send().await;
+    assert_eq!(echoed.header("x-request-id"), Some("abc-123"));
+}
+
+#[tokio::test]
+async fn renders_the_index_page() {
+    let app = TestApp::new().await;
+    let response = app.get("/blog").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    let html = response.text();
+    assert!(html.contains("Moby Dick") && html.contains("A Tale of Two Cities"));
+    let redirect = app.get("/blog/").send().await;
+    assert_eq!(redirect.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(redirect.header("location"), Some("/blog"));
+}
+
+#[tokio::test]
+async fn renders_a_post_page_with_its_comments() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.post("/posts/2/comments")
+        .token(&alice)
+        .json(json!({ "body": "A whale of a tale" }))
+        .send()
+        .await;
+    let response = app.get("/blog/posts/2").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    let html = response.text();
+    assert!(html.contains("<p>Call me Ishmael.</p>"), "{html}");
+    assert!(html.contains("A whale of a tale"), "{html}");
+}
+
+#[tokio::test]
+async fn renders_author_and_tag_pages() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.add_post(&alice, json!({ "title": "Tagged", "body": "Body", "tags": ["rust"] }))
+        .await;
+    let by_author = app.get("/blog/authors/Melville").send().await;
+    assert_eq!(by_author.status, StatusCode::OK);
+    assert!(by_author.text().contains("Moby Dick"));
+    let by_tag = app.get("/blog/tags/rust").send().await;
+    assert_eq!(by_tag.status, StatusCode::OK);
+    assert!(by_tag.text().contains("Tagged"));
+}
+
+#[tokio::test]
+async fn shows_a_not_found_page() {
+    let app = TestApp::new().await;
+    for uri in [
+        "/blog/posts/99",
+        "/blog/posts/abc",
+        "/blog/authors/nobody",
+        "/blog/no-such-page",
+        "/blog?page=9",
+    ] {
+        let response = app.get(uri).send().await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{uri}");
+        assert!(response.text().contains("Page not found"), "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn hides_drafts_from_the_pages() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app
+        .add_post(&alice, json!({ "title": "Secret", "body": "Body", "status": "draft" }))
+        .await;
+    let response = app.get(&format!("/blog/posts/{id}")).token(&alice).send().await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(!app.get("/blog").send().await.text().contains("Secret"));
+}
+
+#[tokio::test]
+async fn dates_pages_by_publication() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app
+        .add_post(&alice, json!({ "title": "Slow cooked", "body": "Body", "status": "draft" }))
+        .await;
+    sqlx::query("UPDATE blog_posts SET created_at = '2020-06-01T00:00:00+00:00' WHERE id = ?")
+        .bind(id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    app.patch(&format!("/posts/{id}"))
+        .token(&alice)
+        .json(json!({ "status": "published" }))
+        .send()
+        .await;
+    let html = app.get("/blog").send().await.text();
+    let newest = html.find("Slow cooked").unwrap();
+    assert!(newest < html.find("Moby Dick").unwrap(), "{html}");
+    assert!(!html.contains("June 2020"), "{html}");
+}
+
+#[tokio::test]
+async fn serves_the_stylesheet() {
+    let app = TestApp::new().await;
+    let response = app.get("/static/style.css").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.header("content-type").unwrap().starts_with("text/css"));
+    let missing = app.get("/static/nothing.css").send().await;
+    assert_eq!(missing.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn serves_rss_and_atom_feeds() {
+    let app = TestApp::new().await;
+    let rss = app.get("/feed.rss").header("host", "blog.test").send().await;
+    assert_eq!(rss.status, StatusCode::OK);
+    assert_eq!(
+        rss.header("content-type"),
+        Some("application/rss+xml; charset=utf-8")
+    );
+    let xml = rss.text();
+    assert!(xml.contains("<link>http://blog.test/blog/posts/2</link>"), "{xml}");
+    assert!(xml.contains("<pubDate>Sat, 2 Jan 2021 00:00:00 +0000</pubDate>"), "{xml}");
+    assert!(xml.contains("<lastBuildDate>Sat, 2 Jan 2021 00:00:00 +0000</lastBuildDate>"), "{xml}");
+    // The tag is a fixed hash of the posts, so it is the same in every build
+    assert_eq!(rss.header("etag"), Some("W/\"7816a7d551fdbc98\""));
+    assert_eq!(
+        rss.header("last-modified"),
+        Some("Sat, 02 Jan 2021 00:00:00 GMT")
+    );
+
+    let atom = app.get("/feed.atom?author=Dickens").send().await;
+    assert_eq!(atom.status, StatusCode::OK);
+    let xml = atom.text();
+    assert!(xml.contains("<updated>2021-01-01T00:00:00+00:00</updated>"), "{xml}");
+    assert!(!xml.contains("Moby Dick"), "{xml}");
+}
+
+#[tokio::test]
+async fn feeds_honour_if_modified_since() {
+    let app = TestApp::new().await;
+    let response = app
+        .get("/feed.atom")
+        .header("if-modified-since", "Sat, 02 Jan 2021 00:00:00 GMT")
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+    assert!(response.body.is_empty());
+    let response = app
+        .get("/feed.rss")
+        .header("if-modified-since", "Fri, 01 Jan 2021 00:00:00 GMT")
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    // A tag that no longer matches wins over a date that still does
+    let response = app
+        .get("/feed.rss")
+        .header("if-none-match", "W/\"0000000000000000\"")
+        .header("if-modified-since", "Sat, 02 Jan 2021 00:00:00 GMT")
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn feeds_honour_if_none_match() {
+    let app = TestApp::new().await;
+    let first = app.get("/feed.atom").send().await;
+    let etag = first.header("etag").unwrap().to_string();
+    let response = app.get("/feed.atom").header("if-none-match", &etag).send().await;
+    assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+    assert!(response.body.is_empty());
+    assert_eq!(response.header("etag"), Some(etag.as_str()));
+    let other = app
+        .get("/feed.atom")
+        .header("if-none-match", "W/\"0000000000000000\"")
+        .send()
+        .await;
+    assert_eq!(other.status, StatusCode::OK);
+
+    // Trashing a post takes it out of the feed, whatever its dates say
+    let admin = app.user("admin", true).await;
+    app.delete("/posts/2").token(&admin).send().await;
+    let response = app.get("/feed.atom").header("if-none-match", &etag).send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.text().contains("Moby Dick"));
+    assert_ne!(response.header("etag"), Some(etag.as_str()));
+}
+
+#[tokio::test]
+async fn feeds_list_posts_by_when_they_were_published() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let draft = app
+        .add_post(&alice, json!({ "title": "Long in the making", "body": "Body", "status": "draft" }))
+        .await;
+    for n in 0..20 {
+        app.add_post(&alice, json!({ "title": format!("Post {n}"), "body": "Body" }))
+            .await;
+    }
+    app.patch(&format!("/posts/{draft}"))
+        .token(&alice)
+        .json(json!({ "status": "published" }))
+        .send()
+        .await;
+    let xml = app.get("/feed.rss").send().await.text();
+    let first = xml.split("<item>").nth(1).unwrap();
+    assert!(first.contains("Long in the making"), "{xml}");
+}
+
+#[tokio::test]
+async fn feeds_reject_a_bad_query() {
+    let app = TestApp::new().await;
+    app.get("/feed.rss?tag=rust&tag=web")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+}
+
+#[tokio::test]
+async fn serves_the_api_docs() {
+    let app = TestApp::new().await;
+    let spec = app.get("/openapi.json").send().await;
+    assert_eq!(spec.status, StatusCode::OK);
+    assert!(spec.json()["paths"]["/posts/{id}"]["get"].is_object());
+    let docs = app.get("/docs/").send().await;
+    assert_eq!(docs.status, StatusCode::OK);
+    assert!(docs.text().contains("swagger-ui"));
+}
+
+#[tokio::test]
+async fn legacy_paths_are_marked_deprecated() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    let response = app.get("/2").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("deprecation"), Some("true"));
+    assert_eq!(response.json()["title"], "Moby Dick");
+    assert_eq!(app.get("/").send().await.status, StatusCode::OK);
+
+    let id = app
+        .post("/add")
+        .token(&admin)
+        .json(json!({ "title": "Old", "body": "School" }))
+        .send()
+        .await;
+    assert_eq!(id.status, StatusCode::CREATED);
+    let uri = format!("/update/{}", id.json());
+    let updated = app
+        .post(&uri)
+        .token(&admin)
+        .json(json!({ "title": "Older", "body": "School" }))
+        .send()
+        .await;
+    assert_eq!(updated.status, StatusCode::OK);
+    let deleted = app.post(&format!("/delete/{}", id.json())).token(&admin).send().await;
+    assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+    assert_eq!(deleted.header("deprecation"), Some("true"));
+}
+
+#[tokio::test]
+async fn legacy_paths_report_errors_too() {
+    let app = TestApp::new().await;
+    let response = app.get("/99").send().await;
+    response.assert_error(StatusCode::NOT_FOUND, "not_found");
+    assert_eq!(response.header("deprecation"), Some("true"));
+    app.post("/add")
+        .json(json!({ "title": "Old", "body": "School" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[tokio::test]
+async fn refuses_oversized_bodies() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let body = "x".repeat(4 * blog_core::MAX_BODY_LEN + 2048);
+    let response = app
+        .post("/posts")
+        .token(&alice)
+        .json(json!({ "title": "Big", "body": body }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn leaves_out_disabled_features() {
+    let mut config = Config::default();
+    config.features.frontend = false;
+    config.features.feeds = false;
+    config.features.docs = false;
+    config.features.legacy_routes = false;
+    config.features.metrics = false;
+    let app = TestApp::with_config(&config).await;
+    for uri in ["/blog", "/static/style.css", "/feed.rss", "/openapi.json", "/2", "/metrics"] {
+        assert_eq!(app.get(uri).send().await.status, StatusCode::NOT_FOUND, "{uri}");
+    }
+    assert_eq!(app.get("/posts/2").send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn allows_configured_cors_origins() {
+    let mut config = Config::default();
+    config.server.cors_origins = vec!["https://reader.test".to_string()];
+    let app = TestApp::with_config(&config).await;
+    let preflight = app
+        .request(axum::http::Method::OPTIONS, "/posts")
+        .header("origin", "https://reader.test")
+        .header("access-control-request-method", "POST")
+        .send()
+        .await;
+    assert_eq!(preflight.status, StatusCode::OK);
+    assert_eq!(
+        preflight.header("access-control-allow-origin"),
+        Some("https://reader.test")
+    );
+    let other = app
+        .get("/posts/2")
+        .header("origin", "https://elsewhere.test")
+        .send()
+        .await;
+    assert_eq!(other.header("access-control-allow-origin"), None);
+}
+
+#[tokio::test]
+async fn exports_metrics() {
+    let app = TestApp::new().await;
+    // The recorder is installed by the first scrape, and shared with the
+    // other tests
+    app.get("/metrics").send().await;
+    app.get("/posts/2").send().await;
+    app.get("/posts/99").send().await;
+    let response = app.get("/metrics").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    let text = response.text();
+    for line in [
+        r#"http_requests_total{method="GET",route="/posts/:id",status="200"}"#,
+        r#"http_requests_total{method="GET",route="/posts/:id",status="404"}"#,
+        r#"http_request_duration_seconds_bucket{method="GET",route="/posts/:id",status="200",le="+Inf"}"#,
+        r#"db_pool_max_connections 10"#,
+        r#"tokio_workers 1"#,
+    ] {
+        assert!(text.contains(line), "{line} not in\n{text}");
+    }
+}
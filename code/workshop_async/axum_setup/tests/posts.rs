@@ -0,0 +1,470 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+use serde_json::json;
+
+#[tokio::test]
+async fn lists_the_seeded_posts() {
+    let app = TestApp::new().await;
+    let response = app.get("/posts").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    let page = response.json();
+    let titles: Vec<_> = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|post| post["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["A Tale of Two Cities", "Moby Dick"]);
+    assert!(page["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn follows_the_cursor_to_the_next_page() {
+    let app = TestApp::new().await;
+    let first = app.get("/posts?limit=1").send().await.json();
+    assert_eq!(first["items"][0]["id"], 1);
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = app.get(&format!("/posts?limit=1&cursor={cursor}")).send().await.json();
+    assert_eq!(second["items"][0]["id"], 2);
+    assert!(second["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn pages_through_posts_by_publication() {
+    let app = TestApp::new().await;
+    let uri = "/posts?limit=1&sort=published&order=desc";
+    let first = app.get(uri).send().await.json();
+    assert_eq!(first["items"][0]["id"], 2);
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = app.get(&format!("{uri}&cursor={cursor}")).send().await.json();
+    assert_eq!(second["items"][0]["id"], 1);
+    assert!(second["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn rejects_a_bad_listing_query() {
+    let app = TestApp::new().await;
+    app.get("/posts?cursor=not-a-cursor")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+    app.get("/posts?sort=colour")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_token() {
+    let app = TestApp::new().await;
+    let response = app.get("/posts").token("blog_nonsense").send().await;
+    response.assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+    assert_eq!(response.header("www-authenticate"), Some("Bearer"));
+}
+
+#[tokio::test]
+async fn accepts_the_scheme_in_any_case() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let draft = app
+        .add_post(&alice, json!({ "title": "Draft", "body": "Body", "status": "draft" }))
+        .await;
+    let response = app
+        .get(&format!("/posts/{draft}"))
+        .header("authorization", &format!("bearer {alice}"))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    app.get("/posts")
+        .header("authorization", &format!("Basic {alice}"))
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[tokio::test]
+async fn gets_a_post_with_its_etag() {
+    let app = TestApp::new().await;
+    let response = app.get("/posts/2").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("etag"), Some("\"1\""));
+    let post = response.json();
+    assert_eq!(post["title"], "Moby Dick");
+    assert_eq!(post["author"], "Melville");
+    assert_eq!(post["status"], "published");
+}
+
+#[tokio::test]
+async fn gets_a_post_body_as_html() {
+    let app = TestApp::new().await;
+    let response = app.get("/posts/2?format=html").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text().trim(), "<p>Call me Ishmael.</p>");
+    let response = app.get("/posts/2").header("accept", "text/html").send().await;
+    assert!(response.header("content-type").unwrap().starts_with("text/html"));
+}
+
+#[tokio::test]
+async fn reports_missing_and_malformed_post_ids() {
+    let app = TestApp::new().await;
+    app.get("/posts/99")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.get("/posts/abc")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+}
+
+#[tokio::test]
+async fn creates_posts_with_increasing_ids() {
+    let app = TestApp::new().await;
+    let token = app.user("alice", false).await;
+    // The id comes from `last_insert_rowid()` in the same statement, so it
+    // must name the row that was just written, and not the seed posts
+    for (expected, title) in [(3, "First"), (4, "Second")] {
+        let response = app
+            .post("/posts")
+            .token(&token)
+            .json(json!({ "title": title, "body": "Text" }))
+            .send()
+            .await;
+        assert_eq!(response.status, StatusCode::CREATED, "{}", response.text());
+        assert_eq!(response.json(), expected);
+        let location = format!("/posts/{expected}");
+        assert_eq!(response.header("location"), Some(location.as_str()));
+        let post = app.get(&location).send().await.json();
+        assert_eq!(post["title"], title);
+        assert_eq!(post["author"], "alice");
+    }
+}
+
+#[tokio::test]
+async fn creating_a_post_needs_a_token() {
+    let app = TestApp::new().await;
+    app.post("/posts")
+        .json(json!({ "title": "Title", "body": "Body" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[tokio::test]
+async fn reports_every_invalid_field() {
+    let app = TestApp::new().await;
+    let token = app.user("alice", false).await;
+    let response = app
+        .post("/posts")
+        .token(&token)
+        .json(json!({ "title": " ", "tags": ["no spaces"] }))
+        .send()
+        .await;
+    response.assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    let fields: Vec<_> = response.json()["error"]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["field"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(fields, ["title", "body", "tags"]);
+}
+
+#[tokio::test]
+async fn rejects_malformed_json() {
+    let app = TestApp::new().await;
+    let token = app.user("alice", false).await;
+    app.post("/posts")
+        .token(&token)
+        .raw("application/json", "{ not json")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+}
+
+#[tokio::test]
+async fn only_admins_post_as_someone_else() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let admin = app.user("admin", true).await;
+    let post = json!({ "title": "Title", "body": "Body", "author": "bob" });
+    app.post("/posts")
+        .token(&alice)
+        .json(post.clone())
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    let id = app.add_post(&admin, post).await;
+    let post = app.get(&format!("/posts/{id}")).send().await.json();
+    assert_eq!(post["author"], "bob");
+}
+
+#[tokio::test]
+async fn drafts_are_hidden_from_everyone_but_their_author() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let bob = app.user("bob", false).await;
+    let id = app
+        .add_post(&alice, json!({ "title": "Draft", "body": "Body", "status": "draft" }))
+        .await;
+    let uri = format!("/posts/{id}");
+    app.get(&uri)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.get(&uri)
+        .token(&bob)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    assert_eq!(app.get(&uri).token(&alice).send().await.status, StatusCode::OK);
+    let listed = app.get("/posts?status=draft").send().await.json();
+    assert_eq!(listed["items"], json!([]));
+}
+
+#[tokio::test]
+async fn scheduling_needs_a_publish_time() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.post("/posts")
+        .token(&alice)
+        .json(json!({ "title": "Later", "body": "Body", "status": "scheduled" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+}
+
+#[tokio::test]
+async fn updates_a_post() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app
+        .add_post(&alice, json!({ "title": "Title", "body": "Body", "tags": ["Rust"] }))
+        .await;
+    let response = app
+        .put(&format!("/posts/{id}"))
+        .token(&alice)
+        .header("if-match", "\"1\"")
+        .json(json!({ "title": "New title", "body": "New body" }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    assert_eq!(response.header("etag"), Some("\"2\""));
+    let post = response.json();
+    assert_eq!(post["title"], "New title");
+    assert_eq!(post["version"], 2);
+    // Tags that aren't mentioned are kept
+    assert_eq!(post["tags"], json!(["rust"]));
+}
+
+#[tokio::test]
+async fn refuses_an_update_to_a_stale_version() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app.add_post(&alice, json!({ "title": "Title", "body": "Body" })).await;
+    let uri = format!("/posts/{id}");
+    let update = json!({ "title": "Title", "body": "Edited" });
+    app.put(&uri)
+        .token(&alice)
+        .json(update.clone())
+        .send()
+        .await;
+    app.put(&uri)
+        .token(&alice)
+        .header("if-match", "\"1\"")
+        .json(update)
+        .send()
+        .await
+        .assert_error(StatusCode::PRECONDITION_FAILED, "precondition_failed");
+}
+
+#[tokio::test]
+async fn refuses_updates_from_other_users() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let update = json!({ "title": "Mine now", "body": "Body" });
+    app.put("/posts/1")
+        .token(&alice)
+        .json(update.clone())
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    app.put("/posts/99")
+        .token(&alice)
+        .json(update.clone())
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    // Someone else's draft is as missing as it is when read
+    let bob = app.user("bob", false).await;
+    let draft = app
+        .add_post(&bob, json!({ "title": "Secret", "body": "Body", "status": "draft" }))
+        .await;
+    let uri = format!("/posts/{draft}");
+    app.put(&uri)
+        .token(&alice)
+        .json(update)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.delete(&uri)
+        .token(&alice)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn patches_only_the_fields_given() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    let response = app
+        .patch("/posts/1")
+        .token(&admin)
+        .json(json!({ "title": "A Tale of Three Cities" }))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.text());
+    let post = response.json();
+    assert_eq!(post["title"], "A Tale of Three Cities");
+    assert_eq!(
+        post["body"],
+        "It was the best of times, it was the worst of times."
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_patch() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    app.patch("/posts/1")
+        .token(&admin)
+        .json(json!({ "title": "" }))
+        .send()
+        .await
+        .assert_error(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed");
+    app.patch("/posts/1")
+        .token(&admin)
+        .header("if-match", "\"7\"")
+        .json(json!({ "title": "Title" }))
+        .send()
+        .await
+        .assert_error(StatusCode::PRECONDITION_FAILED, "precondition_failed");
+}
+
+#[tokio::test]
+async fn deletes_a_post() {
+    let app = TestApp::new().await;
+    let admin = app.user("admin", true).await;
+    let response = app.delete("/posts/2").token(&admin).send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    app.get("/posts/2")
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+    app.delete("/posts/2")
+        .token(&admin)
+        .send()
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "not_found");
+}
+
+#[tokio::test]
+async fn refuses_deletes_from_other_users() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.delete("/posts/2")
+        .token(&alice)
+        .send()
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+    app.delete("/posts/2")
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[tokio::test]
+async fn searches_posts() {
+    let app = TestApp::new().await;
+    let response = app.get("/search?q=ishmael").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    let hits = response.json();
+    assert_eq!(hits.as_array().unwrap().len(), 1);
+    assert_eq!(hits[0]["id"], 2);
+    assert!(hits[0]["snippet"].as_str().unwrap().contains("<mark>"));
+}
+
+#[tokio::test]
+async fn escapes_search_highlights() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.add_post(
+        &alice,
+        json!({ "title": "<script>alert(1)</script> kraken", "body": "A <b>kraken</b> & more" }),
+    )
+    .await;
+    let hits = app.get("/search?q=kraken").send().await.json();
+    assert_eq!(
+        hits[0]["title_highlight"],
+        "&lt;script&gt;alert(1)&lt;/script&gt; <mark>kraken</mark>"
+    );
+    assert_eq!(hits[0]["snippet"], "A &lt;b&gt;<mark>kraken</mark>&lt;/b&gt; &amp; more");
+}
+
+#[tokio::test]
+async fn search_follows_edits() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    let id = app.add_post(&alice, json!({ "title": "Walrus", "body": "Body" })).await;
+    // Rendering the body writes to the row without touching the index
+    app.get(&format!("/posts/{id}?format=html")).send().await;
+    app.put(&format!("/posts/{id}"))
+        .token(&alice)
+        .json(json!({ "title": "Narwhal", "body": "Body" }))
+        .send()
+        .await;
+    let old = app.get("/search?q=walrus").send().await.json();
+    assert_eq!(old, json!([]));
+    let new = app.get("/search?q=narwhal").send().await.json();
+    assert_eq!(new[0]["id"], id);
+}
+
+#[tokio::test]
+async fn search_needs_a_query() {
+    let app = TestApp::new().await;
+    app.get("/search")
+        .send()
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "bad_request");
+}
+
+#[tokio::test]
+async fn counts_tags_and_filters_by_them() {
+    let app = TestApp::new().await;
+    let alice = app.user("alice", false).await;
+    app.add_post(&alice, json!({ "title": "One", "body": "Body", "tags": ["rust", "web"] }))
+        .await;
+    app.add_post(&alice, json!({ "title": "Two", "body": "Body", "tags": ["rust"] }))
+        .await;
+    let tags = app.get("/tags").send().await.json();
+    assert_eq!(
+        tags,
+        json!([{ "name": "rust", "posts": 2 }, { "name": "web", "posts": 1 }])
+    );
+    let tagged = app.get("/posts?tag=web").send().await.json();
+    assert_eq!(tagged["items"][0]["title"], "One");
+    assert_eq!(tagged["items"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn tag_counts_reject_an_invalid_token() {
+    let app = TestApp::new().await;
+    app.get("/tags")
+        .token("blog_nonsense")
+        .send()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
@@ -0,0 +1,55 @@
+//! Runs the server on a real socket, to check how it stops.
+
+use axum::routing::get;
+use axum::{Extension, Router};
+use blog_core::db::get_connection_pool;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Notify};
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn slow_requests_dont_hold_up_a_shutdown() {
+    let pool = get_connection_pool("sqlite::memory:").await.unwrap();
+    let started = std::sync::Arc::new(Notify::new());
+    // Holds a database connection for far longer than the drain timeout
+    let slow = |Extension(pool): Extension<sqlx::SqlitePool>,
+                Extension(started): Extension<std::sync::Arc<Notify>>| async move {
+        let _connection = pool.acquire().await.unwrap();
+        started.notify_one();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+    };
+    let app = Router::new()
+        .route("/slow", get(slow))
+        .layer(Extension(pool.clone()))
+        .layer(Extension(started.clone()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (stop, signal) = oneshot::channel::<()>();
+    let server = tokio::spawn(axum_setup::serve(
+        listener,
+        app,
+        pool,
+        Vec::new(),
+        CancellationToken::new(),
+        Duration::from_millis(200),
+        async move {
+            signal.await?;
+            Ok(())
+        },
+    ));
+
+    let mut client = TcpStream::connect(address).await.unwrap();
+    client
+        .write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    started.notified().await;
+    stop.send(()).unwrap();
+    let stopped = tokio::time::timeout(Duration::from_secs(5), server).await;
+    stopped
+        .expect("the server was still running after the drain timeout")
+        .unwrap()
+        .unwrap();
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use blog_core::db::{
+    add_user, get_connection_pool, get_user_by_name, issue_token, list_users, revoke_token,
+    run_migrations,
+};
+use blog_core::validate_author;
+use clap::Parser;
+
+/// Local administration for the blog database: users and API tokens.
+#[derive(Parser, Debug)]
+#[clap(name = "blog_admin", version = "1.0")]
+struct Opts {
+    #[clap(subcommand)]
+    subcmd: SubCommand,
+}
+
+#[derive(Parser, Debug)]
+enum SubCommand {
+    #[clap(name = "add-user")]
+    AddUser {
+        name: String,
+        /// Allow this user to edit and delete everyone's posts
+        #[clap(long)]
+        admin: bool,
+    },
+    #[clap(name = "list-users")]
+    ListUsers,
+    /// Print a new API token for the user. It can't be shown again.
+    #[clap(name = "issue-token")]
+    IssueToken {
+        name: String,
+        #[clap(long)]
+        label: Option<String>,
+    },
+    #[clap(name = "revoke-token")]
+    RevokeToken { id: i32 },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Opts::parse();
+
+    // Read the .env file and apply it
+    dotenvy::dotenv().ok();
+    let database_url = std::env::var("DATABASE_URL")?;
+    let pool = get_connection_pool(&database_url).await?;
+    run_migrations(pool.clone()).await?;
+
+    match args.subcmd {
+        SubCommand::AddUser { name, admin } => {
+            // User names become post authors, so they follow the same rules
+            let name = validate_author(&name).map_err(|errors| {
+                let messages: Vec<String> = errors
+                    .iter()
+                    .map(|e| format!("{} {}", e.field, e.message))
+                    .collect();
+                anyhow!(messages.join(", "))
+            })?;
+            let id = add_user(pool, name, admin).await?;
+            println!("New User ID: {id}");
+        }
+        SubCommand::ListUsers => {
+            for user in list_users(pool).await? {
+                let role = if user.is_admin { " (admin)" } else { "" };
+                println!("{}: {}{role}", user.id, user.name);
+            }
+        }
+        SubCommand::IssueToken { name, label } => {
+            let user = get_user_by_name(pool.clone(), &name).await?;
+            let issued = issue_token(pool, user.id, label).await?;
+            println!("Token ID: {}", issued.id);
+            println!("{}", issued.token);
+        }
+        SubCommand::RevokeToken { id } => {
+            revoke_token(pool, id).await?;
+            println!("Revoked Token ID: {id}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,393 @@
+mod report;
+
+use anyhow::{anyhow, bail, Context, Result};
+use blog_core::{BlogPost, CreatePost, Page, UpdatePost};
+use clap::{Parser, ValueEnum};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use report::Recorder;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+/// Puts the blog server under load with a mix of API calls, and reports
+/// how quickly it answered.
+#[derive(Parser, Debug)]
+#[clap(name = "blog_bench", version = "1.0")]
+struct Opts {
+    #[clap(long, default_value = "http://localhost:3001")]
+    url: String,
+    /// API token, as issued by `blog_admin issue-token`. Needed to create,
+    /// update and delete posts
+    #[clap(long, env = "BLOG_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Start this many requests a second, however long they take to answer
+    #[clap(long, conflicts_with = "concurrency")]
+    rate: Option<u32>,
+    /// Keep this many requests in flight, sending the next as soon as one is
+    /// answered. The default, with 10, unless --rate is given
+    #[clap(long)]
+    concurrency: Option<usize>,
+    /// How long to run for, in seconds
+    #[clap(long, default_value_t = 10)]
+    duration: u64,
+    /// Relative weights of each call: list, show, create, update and delete
+    #[clap(long, default_value = "list=50,show=35,create=5,update=5,delete=5")]
+    mix: Mix,
+    /// Seconds covered by each row of the timeline
+    #[clap(long, default_value_t = 1.0)]
+    interval: f64,
+    /// Seconds to wait for an answer before counting a request as failed
+    #[clap(long, default_value_t = 10)]
+    timeout: u64,
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    /// Write the results to this file rather than stdout
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    /// A row per operation; the timeline is left out
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Operation {
+    List,
+    Show,
+    Create,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    fn changes_posts(self) -> bool {
+        matches!(self, Operation::Create | Operation::Update | Operation::Delete)
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::List => "list",
+            Operation::Show => "show",
+            Operation::Create => "create",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+        })
+    }
+}
+
+/// How often each operation is picked, as `list=50,show=35,...`.
+/// Operations left out are never picked.
+#[derive(Debug, Clone)]
+struct Mix(Vec<(Operation, u32)>);
+
+impl FromStr for Mix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut weights = Vec::new();
+        for part in s.split(',') {
+            let (name, weight) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected operation=weight, not {part:?}"))?;
+            let operation = Operation::from_str(name.trim(), true).map_err(|err| anyhow!(err))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .with_context(|| format!("bad weight for {operation}"))?;
+            weights.push((operation, weight));
+        }
+        if weights.iter().all(|(_, weight)| *weight == 0) {
+            bail!("at least one weight must be more than 0");
+        }
+        Ok(Mix(weights))
+    }
+}
+
+/// What the workers share: the server, the mix, the posts they know about,
+/// and the results so far.
+struct Workload {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    operations: Vec<Operation>,
+    weights: WeightedIndex<u32>,
+    /// Posts to show: the published ones found at the start, and the ones
+    /// created since.
+    visible: Mutex<Vec<i32>>,
+    /// Posts this run created, which are the only ones it updates or deletes.
+    own: Mutex<Vec<i32>>,
+    recorder: Recorder,
+}
+
+impl Workload {
+    /// Picks an operation from the mix, sends it and records how it went.
+    async fn send_one(&self) {
+        let operation = self.operations[self.weights.sample(&mut rand::thread_rng())];
+        let sent = Instant::now();
+        let (operation, result) = self.call(operation).await;
+        self.recorder.record(operation, sent, result.err());
+    }
+
+    /// Returns the operation actually sent: shows need a post to show, and
+    /// updates and deletes one of our own, so until there is one they list
+    /// or create instead.
+    async fn call(&self, operation: Operation) -> (Operation, Result<(), String>) {
+        match operation {
+            Operation::List => (operation, self.list().await),
+            Operation::Show => match random(&self.visible) {
+                Some(id) => (operation, self.show(id).await),
+                None => (Operation::List, self.list().await),
+            },
+            Operation::Create => (operation, self.create().await),
+            Operation::Update => match random(&self.own) {
+                Some(id) => (operation, self.update(id).await),
+                None => (Operation::Create, self.create().await),
+            },
+            Operation::Delete => {
+                let taken = {
+                    let mut own = self.own.lock().unwrap();
+                    match own.len() {
+                        0 => None,
+                        len => Some(own.swap_remove(rand::thread_rng().gen_range(0..len))),
+                    }
+                };
+                match taken {
+                    Some(id) => (operation, self.delete(id).await),
+                    None => (Operation::Create, self.create().await),
+                }
+            }
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{path}", self.url));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn list(&self) -> Result<(), String> {
+        let response = self
+            .request(reqwest::Method::GET, "/posts?limit=20")
+            .send()
+            .await;
+        check(response).map(drop)
+    }
+
+    async fn show(&self, id: i32) -> Result<(), String> {
+        let response = self
+            .request(reqwest::Method::GET, &format!("/posts/{id}"))
+            .send()
+            .await;
+        check(response).map(drop)
+    }
+
+    async fn create(&self) -> Result<(), String> {
+        let number = rand::thread_rng().gen::<u32>();
+        let post = CreatePost {
+            title: format!("Benchmark post {number}"),
+            body: "Written by blog_bench.".to_string(),
+            tags: vec!["bench".to_string()],
+            ..CreatePost::default()
+        };
+        let response = self
+            .request(reqwest::Method::POST, "/posts")
+            .json(&post)
+            .send()
+            .await;
+        let id = check(response)?
+            .json::<i32>()
+            .await
+            .map_err(|_| "unexpected response".to_string())?;
+        self.own.lock().unwrap().push(id);
+        self.visible.lock().unwrap().push(id);
+        Ok(())
+    }
+
+    async fn update(&self, id: i32) -> Result<(), String> {
+        let post = UpdatePost {
+            title: format!("Benchmark post {id}, edited"),
+            body: "Written by blog_bench, then changed.".to_string(),
+            ..UpdatePost::default()
+        };
+        let response = self
+            .request(reqwest::Method::PUT, &format!("/posts/{id}"))
+            .json(&post)
+            .send()
+            .await;
+        check(response).map(drop)
+    }
+
+    async fn delete(&self, id: i32) -> Result<(), String> {
+        self.visible.lock().unwrap().retain(|visible| *visible != id);
+        let response = self
+            .request(reqwest::Method::DELETE, &format!("/posts/{id}"))
+            .send()
+            .await;
+        check(response).map(drop)
+    }
+}
+
+fn random(ids: &Mutex<Vec<i32>>) -> Option<i32> {
+    ids.lock().unwrap().choose(&mut rand::thread_rng()).copied()
+}
+
+/// Names what went wrong, briefly enough to be counted: `HTTP 404`,
+/// `timeout` and so on.
+fn check(response: reqwest::Result<reqwest::Response>) -> Result<reqwest::Response, String> {
+    match response {
+        Ok(response) if response.status().is_success() => Ok(response),
+        Ok(response) => Err(format!("HTTP {}", response.status().as_u16())),
+        Err(err) if err.is_timeout() => Err("timeout".to_string()),
+        Err(err) if err.is_connect() => Err("connection failed".to_string()),
+        Err(_) => Err("request failed".to_string()),
+    }
+}
+
+/// Starts a request every `1/rate` seconds until `duration` is up, whether
+/// or not the earlier ones have been answered, then waits for them all.
+async fn run_at_rate(workload: Arc<Workload>, rate: u32, duration: Duration) {
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / f64::from(rate)));
+    let stop = tokio::time::sleep(duration);
+    tokio::pin!(stop);
+    let mut in_flight = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = &mut stop => break,
+        }
+        let workload = workload.clone();
+        in_flight.spawn(async move { workload.send_one().await });
+        while in_flight.try_join_next().is_some() {}
+    }
+    while in_flight.join_next().await.is_some() {}
+}
+
+/// Runs `workers` loops that each send a request as soon as their last one
+/// was answered, until `duration` is up.
+async fn run_with_concurrency(workload: Arc<Workload>, workers: usize, duration: Duration) {
+    let deadline = Instant::now() + duration;
+    let mut running = JoinSet::new();
+    for _ in 0..workers {
+        let workload = workload.clone();
+        running.spawn(async move {
+            while Instant::now() < deadline {
+                workload.send_one().await;
+            }
+        });
+    }
+    while running.join_next().await.is_some() {}
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opts = Opts::parse();
+    let (operations, weights): (Vec<Operation>, Vec<u32>) = opts.mix.0.iter().copied().unzip();
+    let writes = operations
+        .iter()
+        .zip(&weights)
+        .any(|(operation, weight)| operation.changes_posts() && *weight > 0);
+    if writes && opts.token.is_none() {
+        bail!("Creating, updating and deleting posts needs a --token; or leave them out of --mix");
+    }
+    if opts.interval <= 0.0 {
+        bail!("--interval must be more than 0");
+    }
+    if opts.rate == Some(0) || opts.concurrency == Some(0) {
+        bail!("--rate and --concurrency must be at least 1");
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(opts.timeout))
+        .build()?;
+    let url = opts.url.trim_end_matches('/').to_string();
+    // Find some posts to show before the clock starts
+    let page = client
+        .get(format!("{url}/posts?limit=100"))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Couldn't list the posts at {url}"))?
+        .json::<Page<BlogPost>>()
+        .await?;
+    let visible = page.items.iter().map(|post| post.id).collect();
+
+    let duration = Duration::from_secs(opts.duration);
+    let mode = match opts.rate {
+        Some(rate) => format!("rate {rate}/s"),
+        None => format!("concurrency {}", opts.concurrency.unwrap_or(10)),
+    };
+    eprintln!("Running at {mode} against {url} for {duration:?}");
+    let workload = Arc::new(Workload {
+        client,
+        url,
+        token: opts.token,
+        operations,
+        weights: WeightedIndex::new(&weights)?,
+        visible: Mutex::new(visible),
+        own: Mutex::new(Vec::new()),
+        recorder: Recorder::new(Duration::from_secs_f64(opts.interval)),
+    });
+    match opts.rate {
+        Some(rate) => run_at_rate(workload.clone(), rate, duration).await,
+        None => {
+            let workers = opts.concurrency.unwrap_or(10);
+            run_with_concurrency(workload.clone(), workers, duration).await
+        }
+    }
+
+    let report = workload.recorder.report(mode);
+    let mut out: Box<dyn std::io::Write> = match &opts.output {
+        Some(path) => Box::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("Couldn't create {}", path.display()))?,
+        ),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match opts.format {
+        Format::Text => report.write_text(&mut out)?,
+        Format::Json => report.write_json(&mut out)?,
+        Format::Csv => report.write_csv(&mut out)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(mix: &str) -> String {
+        format!("{:#}", Mix::from_str(mix).unwrap_err())
+    }
+
+    #[test]
+    fn parses_a_mix() {
+        let Mix(weights) = Mix::from_str("list=3, Show = 1,delete=0").unwrap();
+        assert_eq!(
+            weights,
+            [(Operation::List, 3), (Operation::Show, 1), (Operation::Delete, 0)]
+        );
+    }
+
+    #[test]
+    fn rejects_a_bad_mix() {
+        assert_eq!(error("list=1,show"), "expected operation=weight, not \"show\"");
+        assert!(error("list=1,browse=2").contains("browse"), "{}", error("list=1,browse=2"));
+        assert!(error("list=lots").starts_with("bad weight for list: "));
+        assert!(error("list=-1").starts_with("bad weight for list: "));
+        assert_eq!(error("list=0,show=0"), "at least one weight must be more than 0");
+        assert_eq!(error(""), "expected operation=weight, not \"\"");
+    }
+}
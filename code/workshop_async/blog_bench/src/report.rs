@@ -0,0 +1,374 @@
+use anyhow::Result;
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::Operation;
+
+/// Latencies are kept in microseconds, up to a minute, to three
+/// significant figures.
+const MAX_LATENCY_MICROS: u64 = 60_000_000;
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, 3).expect("the bounds are valid")
+}
+
+#[derive(Default)]
+struct Tally {
+    latency: Option<Histogram<u64>>,
+    requests: u64,
+    errors: u64,
+}
+
+impl Tally {
+    fn record(&mut self, latency: Duration, failed: bool) {
+        let micros = (latency.as_micros() as u64).clamp(1, MAX_LATENCY_MICROS);
+        self.latency
+            .get_or_insert_with(new_histogram)
+            .record(micros)
+            .expect("clamped into range");
+        self.requests += 1;
+        if failed {
+            self.errors += 1;
+        }
+    }
+
+    fn percentiles(&self) -> Percentiles {
+        let Some(latency) = &self.latency else {
+            return Percentiles::default();
+        };
+        let millis = |micros: u64| micros as f64 / 1000.0;
+        Percentiles {
+            p50_ms: millis(latency.value_at_quantile(0.5)),
+            p90_ms: millis(latency.value_at_quantile(0.9)),
+            p99_ms: millis(latency.value_at_quantile(0.99)),
+            p999_ms: millis(latency.value_at_quantile(0.999)),
+            max_ms: millis(latency.max()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tallies {
+    total: Tally,
+    operations: BTreeMap<Operation, Tally>,
+    /// One per reporting interval, by when the response came back.
+    timeline: Vec<Tally>,
+    /// `HTTP 404`, `timeout` and so on.
+    errors: BTreeMap<String, u64>,
+}
+
+/// Collects the outcome of every request, shared by all the workers.
+pub struct Recorder {
+    started: Instant,
+    interval: Duration,
+    tallies: Mutex<Tallies>,
+}
+
+impl Recorder {
+    pub fn new(interval: Duration) -> Self {
+        Recorder {
+            started: Instant::now(),
+            interval,
+            tallies: Mutex::new(Tallies::default()),
+        }
+    }
+
+    /// Records a request sent at `sent`, and what went wrong if anything.
+    pub fn record(&self, operation: Operation, sent: Instant, error: Option<String>) {
+        self.record_at(operation, sent, Instant::now(), error);
+    }
+
+    /// Records a request sent at `sent` and answered at `now`, in the
+    /// interval `now` falls in.
+    fn record_at(
+        &self,
+        operation: Operation,
+        sent: Instant,
+        now: Instant,
+        error: Option<String>,
+    ) {
+        let latency = now - sent;
+        let slot = ((now - self.started).as_secs_f64() / self.interval.as_secs_f64()) as usize;
+        let failed = error.is_some();
+        let mut tallies = self.tallies.lock().unwrap();
+        tallies.total.record(latency, failed);
+        tallies
+            .operations
+            .entry(operation)
+            .or_default()
+            .record(latency, failed);
+        if tallies.timeline.len() <= slot {
+            tallies.timeline.resize_with(slot + 1, Tally::default);
+        }
+        tallies.timeline[slot].record(latency, failed);
+        if let Some(error) = error {
+            *tallies.errors.entry(error).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self, mode: String) -> Report {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let tallies = self.tallies.lock().unwrap();
+        let summary = |operation: String, tally: &Tally| OperationReport {
+            operation,
+            requests: tally.requests,
+            errors: tally.errors,
+            throughput: tally.requests as f64 / elapsed,
+            latency: tally.percentiles(),
+        };
+        let interval = self.interval.as_secs_f64();
+        Report {
+            mode,
+            elapsed_secs: elapsed,
+            total: summary("all".to_string(), &tallies.total),
+            operations: tallies
+                .operations
+                .iter()
+                .map(|(operation, tally)| summary(operation.to_string(), tally))
+                .collect(),
+            timeline: tallies
+                .timeline
+                .iter()
+                .enumerate()
+                .map(|(slot, tally)| IntervalReport {
+                    end_secs: (slot + 1) as f64 * interval,
+                    requests: tally.requests,
+                    errors: tally.errors,
+                    throughput: tally.requests as f64 / interval,
+                    latency: tally.percentiles(),
+                })
+                .collect(),
+            errors: tallies.errors.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Percentiles {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OperationReport {
+    pub operation: String,
+    pub requests: u64,
+    pub errors: u64,
+    /// Requests per second, over the whole run.
+    pub throughput: f64,
+    pub latency: Percentiles,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IntervalReport {
+    /// Seconds from the start of the run to the end of this interval.
+    pub end_secs: f64,
+    pub requests: u64,
+    pub errors: u64,
+    pub throughput: f64,
+    pub latency: Percentiles,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// How the load was applied, such as `rate 100/s` or `concurrency 10`.
+    pub mode: String,
+    pub elapsed_secs: f64,
+    pub total: OperationReport,
+    pub operations: Vec<OperationReport>,
+    pub timeline: Vec<IntervalReport>,
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl Report {
+    pub fn write_text(&self, out: &mut impl Write) -> Result<()> {
+        writeln!(
+            out,
+            "{} for {:.1}s: {} requests, {} errors, {:.1} requests/s",
+            self.mode,
+            self.elapsed_secs,
+            self.total.requests,
+            self.total.errors,
+            self.total.throughput
+        )?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<10} {:>9} {:>7} {:>9} {:>8} {:>8} {:>8} {:>9} {:>8}",
+            "operation", "requests", "errors", "req/s", "p50 ms", "p90 ms", "p99 ms", "p99.9 ms", "max ms"
+        )?;
+        for row in std::iter::once(&self.total).chain(&self.operations) {
+            let latency = &row.latency;
+            writeln!(
+                out,
+                "{:<10} {:>9} {:>7} {:>9.1} {:>8.2} {:>8.2} {:>8.2} {:>9.2} {:>8.2}",
+                row.operation,
+                row.requests,
+                row.errors,
+                row.throughput,
+                latency.p50_ms,
+                latency.p90_ms,
+                latency.p99_ms,
+                latency.p999_ms,
+                latency.max_ms
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:>8} {:>9} {:>7} {:>9} {:>8} {:>8}",
+            "until s", "requests", "errors", "req/s", "p50 ms", "p99 ms"
+        )?;
+        for interval in &self.timeline {
+            writeln!(
+                out,
+                "{:>8.1} {:>9} {:>7} {:>9.1} {:>8.2} {:>8.2}",
+                interval.end_secs,
+                interval.requests,
+                interval.errors,
+                interval.throughput,
+                interval.latency.p50_ms,
+                interval.latency.p99_ms
+            )?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "Errors:")?;
+            for (error, count) in &self.errors {
+                writeln!(out, "  {error}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// One row per operation, and one for them all, so that runs can be
+    /// compared in a spreadsheet. The timeline is only in the JSON.
+    pub fn write_csv(&self, out: &mut impl Write) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(out);
+        csv.write_record([
+            "mode", "operation", "requests", "errors", "throughput", "p50_ms", "p90_ms",
+            "p99_ms", "p999_ms", "max_ms",
+        ])?;
+        for row in std::iter::once(&self.total).chain(&self.operations) {
+            let latency = &row.latency;
+            csv.write_record([
+                self.mode.clone(),
+                row.operation.clone(),
+                row.requests.to_string(),
+                row.errors.to_string(),
+                format!("{:.3}", row.throughput),
+                format!("{:.3}", latency.p50_ms),
+                format!("{:.3}", latency.p90_ms),
+                format!("{:.3}", latency.p99_ms),
+                format!("{:.3}", latency.p999_ms),
+                format!("{:.3}", latency.max_ms),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// Checks `actual` is within the histogram's three significant figures.
+    #[track_caller]
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected / 500.0,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn slots_requests_by_when_they_were_answered() {
+        let recorder = Recorder::new(SECOND);
+        let start = recorder.started;
+        let at = |millis| start + Duration::from_millis(millis);
+        // Sent in the first interval but answered in the second
+        recorder.record_at(Operation::List, at(900), at(1100), None);
+        recorder.record_at(Operation::Show, at(1200), at(1300), None);
+        let error = Some("HTTP 500".to_string());
+        recorder.record_at(Operation::Create, at(3000), at(3500), error);
+
+        let report = recorder.report("rate 1/s".to_string());
+        let timeline: Vec<_> = report
+            .timeline
+            .iter()
+            .map(|interval| (interval.end_secs, interval.requests, interval.errors))
+            .collect();
+        assert_eq!(timeline, [(1.0, 0, 0), (2.0, 2, 0), (3.0, 0, 0), (4.0, 1, 1)]);
+        assert_eq!(report.timeline[1].throughput, 2.0);
+        assert_eq!(report.timeline[0].latency.max_ms, 0.0);
+        assert_close(report.timeline[1].latency.max_ms, 200.0);
+        assert_close(report.timeline[3].latency.p50_ms, 500.0);
+
+        assert_eq!((report.total.requests, report.total.errors), (3, 1));
+        let operations: Vec<_> = report
+            .operations
+            .iter()
+            .map(|row| (row.operation.as_str(), row.requests, row.errors))
+            .collect();
+        assert_eq!(operations, [("list", 1, 0), ("show", 1, 0), ("create", 1, 1)]);
+        assert_eq!(report.errors, BTreeMap::from([("HTTP 500".to_string(), 1)]));
+    }
+
+    #[test]
+    fn reports_latency_percentiles() {
+        let recorder = Recorder::new(Duration::from_secs(10));
+        let start = recorder.started;
+        // 1ms, 2ms, ... 1000ms
+        for millis in 1..=1000 {
+            let answered = start + Duration::from_millis(millis);
+            recorder.record_at(Operation::Show, start, answered, None);
+        }
+
+        let latency = recorder.report("concurrency 1".to_string()).total.latency;
+        assert_close(latency.p50_ms, 500.0);
+        assert_close(latency.p90_ms, 900.0);
+        assert_close(latency.p99_ms, 990.0);
+        assert_close(latency.p999_ms, 999.0);
+        assert_close(latency.max_ms, 1000.0);
+    }
+
+    #[test]
+    fn writes_a_row_per_operation_and_interval() {
+        let recorder = Recorder::new(SECOND);
+        let start = recorder.started;
+        let answered = start + Duration::from_millis(1500);
+        recorder.record_at(Operation::Delete, start, answered, Some("timeout".to_string()));
+
+        let mut text = Vec::new();
+        recorder.report("rate 1/s".to_string()).write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let row = |label: &str| {
+            text.lines()
+                .find(|line| line.split_whitespace().next() == Some(label))
+                .unwrap_or_else(|| panic!("no {label} row in\n{text}"))
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(row("all")[..3], ["all", "1", "1"]);
+        assert_eq!(row("delete")[..3], ["delete", "1", "1"]);
+        assert_eq!(row("1.0")[..3], ["1.0", "0", "0"]);
+        assert_eq!(row("2.0")[..4], ["2.0", "1", "1", "1.0"]);
+        assert!(text.ends_with("Errors:\n  timeout: 1\n"), "{text}");
+    }
+}
@@ -1,18 +1,19 @@
-use serde::{Deserialize, Serialize};
+use blog_core::{
+    BlogPost, Comment, CommentQuery, CommentStatus, CreateComment, CreatePost, ModerateComment, PostFormat, PostQuery, DiffQuery, ListQuery, Page, PatchPost, PostRevision, PostStatus,
+    RevisionDiff, SearchHit, SearchQuery, SortField, SortOrder,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Parser;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct BlogPost {
-    id: i32,
-    date: String,
-    title: String,
-    body: String,
-    author: String,
-}
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MATCH};
+use reqwest::StatusCode;
 
 #[derive(Parser, Debug)]
 #[clap(name = "blog_client", version = "1.0", author = "Your Name")]
 struct Opts {
+    /// API token, as issued by `blog_admin issue-token`. Needed to change
+    /// posts, and to see unpublished ones
+    #[clap(long, env = "BLOG_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
@@ -20,32 +21,216 @@ struct Opts {
 #[derive(Parser, Debug)]
 enum SubCommand {
     #[clap(name = "list")]
-    List,
+    List {
+        #[clap(long)]
+        limit: Option<u32>,
+        #[clap(long)]
+        cursor: Option<String>,
+        #[clap(long)]
+        offset: Option<u32>,
+        #[clap(long)]
+        sort: Option<SortField>,
+        #[clap(long)]
+        order: Option<SortOrder>,
+        #[clap(long)]
+        author: Option<String>,
+        /// Only posts created on or after this date (YYYY-MM-DD)
+        #[clap(long)]
+        from: Option<NaiveDate>,
+        /// Only posts created on or before this date (YYYY-MM-DD)
+        #[clap(long)]
+        to: Option<NaiveDate>,
+        /// draft, scheduled, published or archived
+        #[clap(long)]
+        status: Option<PostStatus>,
+        /// Only posts with this tag
+        #[clap(long)]
+        tag: Option<String>,
+        /// Keep following the next cursor until every page has been listed
+        #[clap(long)]
+        all: bool,
+    },
+    #[clap(name = "search")]
+    Search {
+        /// Words that must all appear; end a word with `*` to match a prefix
+        query: String,
+        #[clap(long)]
+        limit: Option<u32>,
+    },
     #[clap(name = "show")]
-    Show { id: i32 },
+    Show {
+        id: i32,
+        /// Print the body rendered from Markdown to HTML instead
+        #[clap(long)]
+        html: bool,
+    },
     #[clap(name = "create")]
-    Create { title: String, body: String, author: String },
+    Create {
+        title: String,
+        body: String,
+        /// Post on someone else's behalf (admins only)
+        #[clap(long)]
+        author: Option<String>,
+        /// draft, scheduled, published or archived (defaults to published)
+        #[clap(long)]
+        status: Option<PostStatus>,
+        /// When a scheduled post goes out (RFC 3339, e.g. 2024-03-01T09:00:00Z)
+        #[clap(long, requires = "status")]
+        publish_at: Option<DateTime<Utc>>,
+        /// Tag the post; may be given more than once
+        #[clap(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Change a post's title and/or body, leaving the rest as it is
+    #[clap(name = "update")]
+    Update {
+        id: i32,
+        #[clap(long)]
+        title: Option<String>,
+        #[clap(long)]
+        body: Option<String>,
+        /// draft, scheduled, published or archived
+        #[clap(long)]
+        status: Option<PostStatus>,
+        /// When a scheduled post goes out (RFC 3339)
+        #[clap(long)]
+        publish_at: Option<DateTime<Utc>>,
+        /// Replace the post's tags; may be given more than once
+        #[clap(long = "tag")]
+        tags: Vec<String>,
+        /// Only apply if the post is still at this version (as shown by `show`)
+        #[clap(long)]
+        version: Option<i32>,
+    },
+    /// List a post's past versions, or diff two of them
+    #[clap(name = "history")]
+    History {
+        id: i32,
+        /// Show the changes from this version...
+        #[clap(long)]
+        diff: Option<i32>,
+        /// ...to this one (defaults to the current version)
+        #[clap(long, requires = "diff")]
+        to: Option<i32>,
+    },
+    /// Make an old version of a post current again
+    #[clap(name = "restore")]
+    Restore { id: i32, version: i32 },
     #[clap(name = "delete")]
-    Delete { id: i32 },
+    Delete {
+        id: i32,
+        /// Only delete if the post is still at this version
+        #[clap(long)]
+        version: Option<i32>,
+    },
+    /// List deleted posts that haven't been purged yet
+    #[clap(name = "trash")]
+    Trash,
+    /// Take a post back out of the trash
+    #[clap(name = "undelete")]
+    Undelete { id: i32 },
+    /// Show the comments on a post, as threads
+    #[clap(name = "comments")]
+    Comments {
+        id: i32,
+        /// pending, approved or spam (only useful to moderators)
+        #[clap(long)]
+        status: Option<CommentStatus>,
+    },
+    /// Comment on a post
+    #[clap(name = "comment")]
+    Comment {
+        id: i32,
+        body: String,
+        /// The comment this one replies to
+        #[clap(long)]
+        reply_to: Option<i32>,
+        /// Your name, when commenting without a token
+        #[clap(long)]
+        author: Option<String>,
+    },
+    /// Approve a comment, or mark it as pending or spam
+    #[clap(name = "moderate")]
+    Moderate { id: i32, comment_id: i32, status: CommentStatus },
+    #[clap(name = "delete-comment")]
+    DeleteComment { id: i32, comment_id: i32 },
 }
 
 #[tokio::main]
 async fn main() {
     let args = Opts::parse();
+    let request_id = uuid::Uuid::new_v4().to_string();
+    report_request_id_on_failure(request_id.clone());
     match args.subcmd {
-        SubCommand::List => {
-            let posts = reqwest::get("http://localhost:3001/")
+        SubCommand::List { limit, cursor, offset, sort, order, author, from, to, status, tag, all } => {
+            let mut query =
+                ListQuery { limit, cursor, offset, sort, order, author, from, to, status, tag };
+            let client = new_client(&request_id);
+            loop {
+                let page = with_token(client.get("http://localhost:3001/posts"), &args.token)
+                    .query(&query)
+                    .send()
+                    .await
+                    .unwrap()
+                    .json::<Page<BlogPost>>()
+                    .await
+                    .unwrap();
+                for post in page.items {
+                    let mut line = format!("{}: {}", post.id, post.title);
+                    if post.status != PostStatus::Published {
+                        line.push_str(&format!(" [{}]", post.status));
+                    }
+                    for tag in &post.tags {
+                        line.push_str(&format!(" #{tag}"));
+                    }
+                    println!("{line}");
+                }
+                match page.next_cursor {
+                    Some(next) if all => {
+                        query.cursor = Some(next);
+                        query.offset = None;
+                    }
+                    Some(next) => {
+                        println!("More posts available: list --cursor {next}");
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+        SubCommand::Search { query, limit } => {
+            let client = new_client(&request_id);
+            let hits = with_token(client.get("http://localhost:3001/search"), &args.token)
+                .query(&SearchQuery { q: query, limit })
+                .send()
                 .await
                 .unwrap()
-                .json::<Vec<BlogPost>>()
+                .json::<Vec<SearchHit>>()
                 .await
                 .unwrap();
-            for post in posts {
-                println!("{}: {}", post.id, post.title);
+            for hit in hits {
+                println!("{}: {}", hit.post.id, hit.title_highlight);
+                println!("    {}", hit.snippet);
             }
         }
-        SubCommand::Show { id } => {
-            let post = reqwest::get(&format!("http://localhost:3001/{}", id))
+        SubCommand::Show { id, html: true } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}", id);
+            let html = with_token(client.get(url), &args.token)
+                .query(&PostQuery { format: Some(PostFormat::Html) })
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            println!("{}", html);
+        }
+        SubCommand::Show { id, html: false } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}", id);
+            let post = with_token(client.get(url), &args.token)
+                .send()
                 .await
                 .unwrap()
                 .json::<BlogPost>()
@@ -53,16 +238,12 @@ async fn main() {
                 .unwrap();
             println!("{:?}", post);
         }
-        SubCommand::Create { title, body, author } => {
-            let post = BlogPost {
-                id: 0,
-                date: String::from(""),
-                title,
-                body,
-                author,
-            };
-            let post = reqwest::Client::new()
-                .post("http://localhost:3001/add")
+        SubCommand::Create { title, body, author, status, publish_at, tags } => {
+            // Without an author, the server uses the token's owner
+            let post = CreatePost { title, body, author, status, publish_at, tags };
+            let post = new_client(&request_id)
+                .post("http://localhost:3001/posts")
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
                 .json(&post)
                 .send()
                 .await
@@ -72,14 +253,262 @@ async fn main() {
                 .unwrap();
             println!("New Post ID: {}", post);
         }
-        SubCommand::Delete { id } => {
-            reqwest::Client::new()
-                .delete(&format!("http://localhost:3001/delete/{}", id))
+        SubCommand::Update { id, title, body, status, publish_at, tags, version } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}", id);
+            // Without --version, edit whatever is current right now
+            let etag = match version {
+                Some(version) => format!("\"{version}\""),
+                None => current_etag(&client, &url, &args.token).await,
+            };
+            let response = client
+                .patch(&url)
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .header(IF_MATCH, etag)
+                .json(&PatchPost {
+                    title,
+                    body,
+                    status,
+                    publish_at,
+                    // Leave the tags alone unless some were given
+                    tags: (!tags.is_empty()).then_some(tags),
+                })
+                .send()
+                .await
+                .unwrap();
+            if response.status() == StatusCode::PRECONDITION_FAILED {
+                report_conflict(&client, &url, &args.token).await;
+            }
+            let post = response.json::<BlogPost>().await.unwrap();
+            println!("{:?}", post);
+        }
+        SubCommand::History { id, diff: None, .. } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}/revisions", id);
+            let revisions = with_token(client.get(url), &args.token)
+                .send()
+                .await
+                .unwrap()
+                .json::<Vec<PostRevision>>()
+                .await
+                .unwrap();
+            for revision in revisions {
+                println!(
+                    "v{} {} ({:?} at {}): {}",
+                    revision.version,
+                    revision.author,
+                    revision.operation,
+                    revision.revised_at,
+                    revision.title
+                );
+            }
+        }
+        SubCommand::History { id, diff: Some(from), to } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}/diff", id);
+            let diff = with_token(client.get(url), &args.token)
+                .query(&DiffQuery { from, to })
+                .send()
+                .await
+                .unwrap()
+                .json::<RevisionDiff>()
+                .await
+                .unwrap();
+            print!("{}{}", diff.title, diff.body);
+        }
+        SubCommand::Restore { id, version } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}", id);
+            let response = client
+                .post(format!("{url}/revisions/{version}/restore"))
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .header(IF_MATCH, current_etag(&client, &url, &args.token).await)
                 .send()
                 .await
                 .unwrap();
+            if response.status() == StatusCode::PRECONDITION_FAILED {
+                report_conflict(&client, &url, &args.token).await;
+            }
+            let post = response.json::<BlogPost>().await.unwrap();
+            println!("{:?}", post);
+        }
+        SubCommand::Delete { id, version } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}", id);
+            let mut request = client
+                .delete(&url)
+                .bearer_auth(args.token.as_deref().unwrap_or_default());
+            if let Some(version) = version {
+                request = request.header(IF_MATCH, format!("\"{version}\""));
+            }
+            let response = request.send().await.unwrap();
+            if response.status() == StatusCode::PRECONDITION_FAILED {
+                report_conflict(&client, &url, &args.token).await;
+            }
+            response.error_for_status().unwrap();
             println!("Deleted Post ID: {}", id);
         }
-        _ => println!("Run with --help for help")
+        SubCommand::Trash => {
+            let client = new_client(&request_id);
+            let posts = client
+                .get("http://localhost:3001/trash")
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .send()
+                .await
+                .unwrap()
+                .json::<Vec<BlogPost>>()
+                .await
+                .unwrap();
+            for post in posts {
+                println!("{:?}", post);
+            }
+        }
+        SubCommand::Comments { id, status } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}/comments", id);
+            let comments = with_token(client.get(url), &args.token)
+                .query(&CommentQuery { status })
+                .send()
+                .await
+                .unwrap()
+                .json::<Vec<Comment>>()
+                .await
+                .unwrap();
+            print_thread(&comments, None, 0);
+        }
+        SubCommand::Comment { id, body, reply_to, author } => {
+            let client = new_client(&request_id);
+            let url = format!("http://localhost:3001/posts/{}/comments", id);
+            let comment = CreateComment { body, author, parent_id: reply_to };
+            let comment = with_token(client.post(url), &args.token)
+                .json(&comment)
+                .send()
+                .await
+                .unwrap()
+                .json::<Comment>()
+                .await
+                .unwrap();
+            println!("New Comment ID: {} ({})", comment.id, comment.status);
+        }
+        SubCommand::Moderate { id, comment_id, status } => {
+            let comment = new_client(&request_id)
+                .patch(format!("http://localhost:3001/posts/{}/comments/{}", id, comment_id))
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .json(&ModerateComment { status })
+                .send()
+                .await
+                .unwrap()
+                .json::<Comment>()
+                .await
+                .unwrap();
+            println!("{:?}", comment);
+        }
+        SubCommand::DeleteComment { id, comment_id } => {
+            new_client(&request_id)
+                .delete(format!("http://localhost:3001/posts/{}/comments/{}", id, comment_id))
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .send()
+                .await
+                .unwrap()
+                .error_for_status()
+                .unwrap();
+            println!("Deleted Comment ID: {}", comment_id);
+        }
+        SubCommand::Undelete { id } => {
+            let client = new_client(&request_id);
+            let post = client
+                .post(format!("http://localhost:3001/posts/{}/undelete", id))
+                .bearer_auth(args.token.as_deref().unwrap_or_default())
+                .send()
+                .await
+                .unwrap()
+                .json::<BlogPost>()
+                .await
+                .unwrap();
+            println!("{:?}", post);
+        }
+    }
+}
+
+/// Prints the replies to `parent` (the top-level comments for `None`),
+/// each followed by its own replies, indented. Replies whose parent isn't in
+/// the list, because it is hidden from us, are shown at the top level.
+fn print_thread(comments: &[Comment], parent: Option<i32>, depth: usize) {
+    let is_top_level = |comment: &Comment| match comment.parent_id {
+        None => true,
+        Some(parent_id) => !comments.iter().any(|other| other.id == parent_id),
+    };
+    for comment in comments {
+        let is_child = match parent {
+            None => is_top_level(comment),
+            Some(parent) => comment.parent_id == Some(parent),
+        };
+        if !is_child {
+            continue;
+        }
+        let indent = "    ".repeat(depth);
+        match comment.status {
+            CommentStatus::Approved => println!("{indent}{} {}:", comment.id, comment.author),
+            status => println!("{indent}{} {} [{status}]:", comment.id, comment.author),
+        }
+        println!("{indent}  {}", comment.body);
+        print_thread(comments, Some(comment.id), depth + 1);
+    }
+}
+
+/// Sent as `X-Request-Id` with every request this run makes, so that the
+/// server's logs for them can be found if something goes wrong.
+fn new_client(request_id: &str) -> reqwest::Client {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-request-id", HeaderValue::from_str(request_id).unwrap());
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap()
+}
+
+/// Any failure panics, so the request id is printed after the panic message.
+fn report_request_id_on_failure(request_id: String) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        eprintln!("Request id: {request_id}");
+    }));
+}
+
+/// Sends the token along if there is one. Anonymous requests only see
+/// published posts.
+fn with_token(request: reqwest::RequestBuilder, token: &Option<String>) -> reqwest::RequestBuilder {
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
     }
 }
+
+async fn current_etag(client: &reqwest::Client, url: &str, token: &Option<String>) -> String {
+    let response = with_token(client.get(url), token).send().await.unwrap();
+    response
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .unwrap_or("*")
+        .to_string()
+}
+
+/// Someone else changed the post first: show what it looks like now, and exit.
+async fn report_conflict(client: &reqwest::Client, url: &str, token: &Option<String>) -> ! {
+    let current = with_token(client.get(url), token)
+        .send()
+        .await
+        .unwrap()
+        .json::<BlogPost>()
+        .await
+        .unwrap();
+    eprintln!(
+        "Conflict: post {} was changed by someone else and is now at version {}.",
+        current.id, current.version
+    );
+    eprintln!("{:?}", current);
+    eprintln!("Nothing was changed. Review it, then re-run with --version {}.", current.version);
+    std::process::exit(1);
+}
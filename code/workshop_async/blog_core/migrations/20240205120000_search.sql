@@ -0,0 +1,28 @@
+-- Full-text index over the posts. It's an external content table, so the
+-- text itself lives only in blog_posts and the triggers keep the index in step.
+CREATE VIRTUAL TABLE blog_posts_fts USING fts5(
+    title,
+    body,
+    author,
+    content = 'blog_posts',
+    content_rowid = 'id'
+);
+
+INSERT INTO blog_posts_fts(blog_posts_fts) VALUES ('rebuild');
+
+CREATE TRIGGER blog_posts_fts_insert AFTER INSERT ON blog_posts BEGIN
+    INSERT INTO blog_posts_fts(rowid, title, body, author)
+    VALUES (new.id, new.title, new.body, new.author);
+END;
+
+CREATE TRIGGER blog_posts_fts_delete AFTER DELETE ON blog_posts BEGIN
+    INSERT INTO blog_posts_fts(blog_posts_fts, rowid, title, body, author)
+    VALUES ('delete', old.id, old.title, old.body, old.author);
+END;
+
+CREATE TRIGGER blog_posts_fts_update AFTER UPDATE ON blog_posts BEGIN
+    INSERT INTO blog_posts_fts(blog_posts_fts, rowid, title, body, author)
+    VALUES ('delete', old.id, old.title, old.body, old.author);
+    INSERT INTO blog_posts_fts(rowid, title, body, author)
+    VALUES (new.id, new.title, new.body, new.author);
+END;
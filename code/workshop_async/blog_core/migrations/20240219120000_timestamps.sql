@@ -0,0 +1,13 @@
+-- Replace the client-supplied `date` text with server-set timestamps, stored
+-- as RFC 3339 text in the same form sqlx encodes a DateTime<Utc>. Dates that
+-- don't parse (including the empty strings the old client sent) become "now".
+ALTER TABLE blog_posts ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+ALTER TABLE blog_posts ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+
+UPDATE blog_posts SET created_at = CASE
+    WHEN datetime(date) IS NOT NULL THEN strftime('%Y-%m-%dT%H:%M:%S+00:00', date)
+    ELSE strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+END;
+UPDATE blog_posts SET updated_at = created_at;
+
+ALTER TABLE blog_posts DROP COLUMN date;
@@ -0,0 +1,3 @@
+-- Bumped on every update, so that writers can tell whether a post has
+-- changed since they read it.
+ALTER TABLE blog_posts ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,27 @@
+-- Every version of a post that gets replaced or deleted is kept here. The
+-- triggers rely on each write to title/body/author also bumping `version`,
+-- which update_blog_post always does.
+CREATE TABLE blog_post_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    post_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    title TEXT,
+    body TEXT,
+    author TEXT,
+    updated_at TEXT NOT NULL,
+    revised_at TEXT NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('update', 'delete')),
+    UNIQUE (post_id, version)
+);
+
+CREATE TRIGGER blog_posts_revision_update BEFORE UPDATE OF title, body, author ON blog_posts BEGIN
+    INSERT INTO blog_post_revisions (post_id, version, title, body, author, updated_at, revised_at, operation)
+    VALUES (old.id, old.version, old.title, old.body, old.author, old.updated_at,
+        strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'), 'update');
+END;
+
+CREATE TRIGGER blog_posts_revision_delete BEFORE DELETE ON blog_posts BEGIN
+    INSERT INTO blog_post_revisions (post_id, version, title, body, author, updated_at, revised_at, operation)
+    VALUES (old.id, old.version, old.title, old.body, old.author, old.updated_at,
+        strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'), 'delete');
+END;
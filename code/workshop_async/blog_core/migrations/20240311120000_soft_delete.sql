@@ -0,0 +1,5 @@
+-- Deleting a post now only stamps deleted_at; the row is removed for good
+-- when the server purges the trash.
+ALTER TABLE blog_posts ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX blog_posts_deleted_at ON blog_posts(deleted_at);
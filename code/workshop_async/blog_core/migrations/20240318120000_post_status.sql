@@ -0,0 +1,9 @@
+-- Posts move from draft (or scheduled) to published, and may be archived.
+-- Everything written before this was public, so it starts out published.
+ALTER TABLE blog_posts ADD COLUMN status TEXT NOT NULL DEFAULT 'published'
+    CHECK (status IN ('draft', 'scheduled', 'published', 'archived'));
+ALTER TABLE blog_posts ADD COLUMN publish_at TEXT;
+
+UPDATE blog_posts SET publish_at = created_at;
+
+CREATE INDEX blog_posts_status_publish_at ON blog_posts(status, publish_at);
@@ -0,0 +1,14 @@
+-- Readers' comments. A reply points at its parent; deleting a comment takes
+-- its replies with it, and purging a post takes all of its comments.
+CREATE TABLE comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    post_id INTEGER NOT NULL REFERENCES blog_posts(id) ON DELETE CASCADE,
+    parent_id INTEGER REFERENCES comments(id) ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'spam')),
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX comments_post_id ON comments(post_id, id);
+CREATE INDEX comments_parent_id ON comments(parent_id);
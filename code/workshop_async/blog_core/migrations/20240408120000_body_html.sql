@@ -0,0 +1,3 @@
+-- The body rendered from Markdown to sanitized HTML, filled in the first
+-- time it is asked for and cleared whenever the post is updated.
+ALTER TABLE blog_posts ADD COLUMN body_html TEXT;
@@ -0,0 +1,11 @@
+-- Only the indexed columns need the FTS row replaced. Caching body_html, or
+-- the scheduler and trash changing status, publish_at or deleted_at, leave
+-- the index as it was.
+DROP TRIGGER blog_posts_fts_update;
+
+CREATE TRIGGER blog_posts_fts_update AFTER UPDATE OF title, body, author ON blog_posts BEGIN
+    INSERT INTO blog_posts_fts(blog_posts_fts, rowid, title, body, author)
+    VALUES ('delete', old.id, old.title, old.body, old.author);
+    INSERT INTO blog_posts_fts(rowid, title, body, author)
+    VALUES (new.id, new.title, new.body, new.author);
+END;
@@ -0,0 +1,28 @@
+-- Since deletes became soft, the last version of a post is recorded when it
+-- goes into the trash, not when it is purged. Undeleting makes that version
+-- current again, so its revision goes; purging removes the post's whole
+-- history along with it.
+DROP TRIGGER blog_posts_revision_delete;
+
+CREATE TRIGGER blog_posts_revision_trash AFTER UPDATE OF deleted_at ON blog_posts
+WHEN old.deleted_at IS NULL AND new.deleted_at IS NOT NULL BEGIN
+    INSERT INTO blog_post_revisions (post_id, version, title, body, author, updated_at, revised_at, operation)
+    VALUES (new.id, new.version, new.title, new.body, new.author, new.updated_at,
+        new.deleted_at, 'delete');
+END;
+
+CREATE TRIGGER blog_posts_revision_undelete AFTER UPDATE OF deleted_at ON blog_posts
+WHEN old.deleted_at IS NOT NULL AND new.deleted_at IS NULL BEGIN
+    DELETE FROM blog_post_revisions
+    WHERE post_id = old.id AND version = old.version AND operation = 'delete';
+END;
+
+CREATE TRIGGER blog_posts_revision_purge AFTER DELETE ON blog_posts BEGIN
+    DELETE FROM blog_post_revisions WHERE post_id = old.id;
+END;
+
+-- Posts already in the trash get the revision they would have had
+INSERT INTO blog_post_revisions (post_id, version, title, body, author, updated_at, revised_at, operation)
+SELECT id, version, title, body, author, updated_at, deleted_at, 'delete'
+FROM blog_posts
+WHERE deleted_at IS NOT NULL;
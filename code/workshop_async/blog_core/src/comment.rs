@@ -0,0 +1,107 @@
+use crate::validation::{FieldError, Validator, MAX_COMMENT_LEN};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a comment is in moderation. Only approved comments are shown to
+/// everyone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "db", sqlx(rename_all = "lowercase"))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Spam,
+}
+
+impl fmt::Display for CommentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Spam => "spam",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for CommentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(CommentStatus::Pending),
+            "approved" => Ok(CommentStatus::Approved),
+            "spam" => Ok(CommentStatus::Spam),
+            _ => Err(format!(
+                "unknown status `{s}` (expected pending, approved or spam)"
+            )),
+        }
+    }
+}
+
+/// A single row of the `comments` table. `parent_id` is set on replies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Comment {
+    pub id: i32,
+    pub post_id: i32,
+    pub parent_id: Option<i32>,
+    pub author: String,
+    pub body: String,
+    pub status: CommentStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of a request to comment on a post.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateComment {
+    #[serde(default)]
+    pub body: String,
+    /// Required from anonymous readers; signed-in users always comment under
+    /// their own name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The comment this one replies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i32>,
+}
+
+impl CreateComment {
+    /// Returns the comment with whitespace trimmed, or every failing field.
+    pub fn validate(self) -> Result<Self, Vec<FieldError>> {
+        let mut v = Validator::default();
+        let comment = CreateComment {
+            body: v.text("body", &self.body, MAX_COMMENT_LEN),
+            author: self.author.map(|author| v.author("author", &author)),
+            parent_id: self.parent_id,
+        };
+        v.finish(comment)
+    }
+}
+
+/// Body of a request to moderate a comment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ModerateComment {
+    pub status: CommentStatus,
+}
+
+/// Query string for listing comments. Filtering by status is only useful to
+/// moderators, since everyone else only sees approved comments (and their
+/// own).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct CommentQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<CommentStatus>,
+}
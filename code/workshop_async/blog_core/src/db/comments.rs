@@ -0,0 +1,92 @@
+use crate::{Comment, CommentStatus};
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::Row;
+
+/// A post's comments, oldest first, optionally only those with `status`.
+/// Replies come after their parents, so a thread can be built in one pass.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn list_comments(
+    pool: sqlx::SqlitePool,
+    post_id: i32,
+    status: Option<CommentStatus>,
+) -> Result<Vec<Comment>> {
+    let comments = sqlx::query_as::<_, Comment>(
+        "SELECT * FROM comments WHERE post_id = ? AND (? IS NULL OR status = ?) ORDER BY id",
+    )
+    .bind(post_id)
+    .bind(status)
+    .bind(status)
+    .fetch_all(&pool)
+    .await?;
+    Ok(comments)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` unless the comment is on that post.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_comment(pool: sqlx::SqlitePool, post_id: i32, id: i32) -> Result<Comment> {
+    let comment =
+        sqlx::query_as::<_, Comment>("SELECT * FROM comments WHERE post_id = ? AND id = ?")
+            .bind(post_id)
+            .bind(id)
+            .fetch_one(&pool)
+            .await?;
+    Ok(comment)
+}
+
+#[tracing::instrument(level = "debug", skip(pool, author, body))]
+pub async fn add_comment(
+    pool: sqlx::SqlitePool,
+    post_id: i32,
+    parent_id: Option<i32>,
+    author: String,
+    body: String,
+    status: CommentStatus,
+) -> Result<i32> {
+    let id = sqlx::query("INSERT INTO comments (post_id, parent_id, author, body, status, created_at) VALUES (?, ?, ?, ?, ?, ?); SELECT last_insert_rowid();")
+        .bind(post_id)
+        .bind(parent_id)
+        .bind(author)
+        .bind(body)
+        .bind(status)
+        .bind(Utc::now())
+        .fetch_one(&pool)
+        .await?
+        .get(0);
+    Ok(id)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` unless the comment is on that post.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn set_comment_status(
+    pool: sqlx::SqlitePool,
+    post_id: i32,
+    id: i32,
+    status: CommentStatus,
+) -> Result<()> {
+    let result = sqlx::query("UPDATE comments SET status = ? WHERE post_id = ? AND id = ?")
+        .bind(status)
+        .bind(post_id)
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
+
+/// Deletes a comment and, through the foreign key, every reply under it.
+/// Fails with `sqlx::Error::RowNotFound` unless the comment is on that post.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn delete_comment(pool: sqlx::SqlitePool, post_id: i32, id: i32) -> Result<()> {
+    let result = sqlx::query("DELETE FROM comments WHERE post_id = ? AND id = ?")
+        .bind(post_id)
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod comments;
+mod posts;
+mod revisions;
+mod tags;
+mod trash;
+mod users;
+
+use anyhow::Result;
+use std::fmt;
+
+pub use comments::{
+    add_comment, delete_comment, get_comment, list_comments, set_comment_status,
+};
+pub use posts::{
+    add_blog_post, delete_blog_post, get_blog_post, get_blog_posts, get_rendered_body,
+    list_blog_posts, publish_scheduled_posts, search_blog_posts, update_blog_post,
+};
+pub use revisions::{get_revision, list_revisions};
+pub use tags::list_tags;
+pub use trash::{
+    get_deleted_blog_post, list_deleted_blog_posts, purge_blog_post, purge_deleted_blog_posts,
+    undelete_blog_post,
+};
+pub use users::{
+    add_user, authenticate_token, get_user_by_name, issue_token, list_users, revoke_token,
+    user_name_taken, IssuedToken,
+};
+
+/// The caller asked for something that can't be answered, such as a
+/// malformed cursor. Returned inside the `anyhow::Error` so that the server
+/// can tell it apart from a database failure.
+#[derive(Debug)]
+pub struct InvalidQuery(pub String);
+
+impl fmt::Display for InvalidQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidQuery {}
+
+/// A conditional write was refused because the row is no longer at the
+/// version the caller expected.
+#[derive(Debug)]
+pub struct VersionConflict {
+    pub current: i32,
+}
+
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the post is now at version {}", self.current)
+    }
+}
+
+impl std::error::Error for VersionConflict {}
+
+pub async fn get_connection_pool(url: &str) -> Result<sqlx::SqlitePool> {
+    let connection_pool = sqlx::SqlitePool::connect(url).await?;
+    Ok(connection_pool)
+}
+
+/// Like `get_connection_pool`, but holding at most `max_connections`
+/// connections open at once.
+pub async fn get_sized_connection_pool(
+    url: &str,
+    max_connections: u32,
+) -> Result<sqlx::SqlitePool> {
+    let connection_pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect(url)
+        .await?;
+    Ok(connection_pool)
+}
+
+/// Applies the migrations embedded from `blog_core/migrations`.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn run_migrations(pool: sqlx::SqlitePool) -> Result<()> {
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(())
+}
+
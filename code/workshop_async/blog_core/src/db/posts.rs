@@ -0,0 +1,403 @@
+use crate::{
+    render_markdown, BlogPost, ListQuery, Page, Publication, SearchHit, SearchQuery, SortField, SortOrder, User,
+};
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, Row, Sqlite};
+
+use super::tags::{attach_tags, set_post_tags};
+use super::{InvalidQuery, VersionConflict};
+
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_blog_posts(pool: sqlx::SqlitePool) -> Result<Vec<BlogPost>> {
+    let posts = sqlx::query_as::<_, BlogPost>("SELECT * FROM blog_posts WHERE deleted_at IS NULL")
+        .fetch_all(&pool)
+        .await?;
+    Ok(posts)
+}
+
+/// Position of the last row of a page, handed to the client as an opaque
+/// string. It remembers the ordering it was issued for so that it can't be
+/// replayed against a different sort.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: SortField,
+    order: SortOrder,
+    key: Option<String>,
+    id: i32,
+}
+
+impl Cursor {
+    fn after(post: &BlogPost, sort: SortField, order: SortOrder) -> Self {
+        let key = match sort {
+            SortField::Id => None,
+            // Must match how sqlx encodes the stored DateTime<Utc>
+            SortField::Date => Some(post.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            SortField::Title => Some(post.title.clone()),
+            SortField::Published => Some(
+                post.publish_at
+                    .unwrap_or(post.created_at)
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            ),
+        };
+        Self { sort, order, key, id: post.id }
+    }
+
+    fn encode(&self) -> Result<String> {
+        Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?))
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        let invalid = || InvalidQuery("invalid cursor".to_string());
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let cursor = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        Ok(cursor)
+    }
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Binds for `(? OR status = 'published' OR author = ?)`, which is how the
+/// queries below keep unpublished posts to their authors and admins.
+pub(super) fn visibility(viewer: Option<&User>) -> (bool, Option<String>) {
+    match viewer {
+        Some(user) => (user.is_admin, Some(user.name.clone())),
+        None => (false, None),
+    }
+}
+
+/// Returns one page of the posts `viewer` may see, filtered and ordered as
+/// requested. Ties in the sort column are broken by id, so paging never
+/// skips or repeats rows.
+#[tracing::instrument(level = "debug", skip(pool, viewer))]
+pub async fn list_blog_posts(
+    pool: sqlx::SqlitePool,
+    query: &ListQuery,
+    viewer: Option<&User>,
+) -> Result<Page<BlogPost>> {
+    let sort = query.sort.unwrap_or_default();
+    let order = query.order.unwrap_or_default();
+    let limit = query.page_size();
+    let cursor = match &query.cursor {
+        Some(_) if query.offset.is_some() => {
+            return Err(InvalidQuery("use either cursor or offset, not both".to_string()).into());
+        }
+        Some(cursor) => Some(Cursor::decode(cursor)?),
+        None => None,
+    };
+
+    let column = sort.column();
+    let (cmp, direction) = match order {
+        SortOrder::Asc => (">", "ASC"),
+        SortOrder::Desc => ("<", "DESC"),
+    };
+
+    let (everything, own) = visibility(viewer);
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM blog_posts WHERE deleted_at IS NULL");
+    qb.push(" AND (")
+        .push_bind(everything)
+        .push(" OR status = 'published' OR author = ")
+        .push_bind(own)
+        .push(")");
+    if let Some(status) = query.status {
+        qb.push(" AND status = ").push_bind(status);
+    }
+    if let Some(author) = &query.author {
+        qb.push(" AND author = ").push_bind(author.clone());
+    }
+    if let Some(tag) = &query.tag {
+        qb.push(" AND id IN (SELECT post_tags.post_id FROM post_tags JOIN tags ON tags.id = post_tags.tag_id WHERE tags.name = ")
+            .push_bind(tag.clone())
+            .push(")");
+    }
+    if let Some(from) = query.from {
+        qb.push(" AND created_at >= ").push_bind(start_of_day(from));
+    }
+    if let Some(to) = query.to.and_then(|to| to.succ_opt()) {
+        qb.push(" AND created_at < ").push_bind(start_of_day(to));
+    }
+    if let Some(cursor) = cursor {
+        if cursor.sort != sort || cursor.order != order {
+            return Err(InvalidQuery("cursor was issued for a different sort".to_string()).into());
+        }
+        match (sort, cursor.key) {
+            (SortField::Id, _) => {
+                qb.push(format!(" AND id {cmp} ")).push_bind(cursor.id);
+            }
+            (_, Some(key)) => {
+                qb.push(format!(" AND ({column} {cmp} "))
+                    .push_bind(key.clone())
+                    .push(format!(" OR ({column} = "))
+                    .push_bind(key)
+                    .push(format!(" AND id {cmp} "))
+                    .push_bind(cursor.id)
+                    .push("))");
+            }
+            (_, None) => return Err(InvalidQuery("invalid cursor".to_string()).into()),
+        }
+    }
+    qb.push(format!(" ORDER BY {column} {direction}"));
+    if sort != SortField::Id {
+        qb.push(format!(", id {direction}"));
+    }
+    // Fetch one extra row to find out whether there is another page
+    qb.push(" LIMIT ").push_bind(limit + 1);
+    if let Some(offset) = query.offset {
+        qb.push(" OFFSET ").push_bind(offset);
+    }
+
+    let mut items = qb.build_query_as::<BlogPost>().fetch_all(&pool).await?;
+    let next_cursor = if items.len() > limit as usize {
+        items.truncate(limit as usize);
+        items
+            .last()
+            .map(|post| Cursor::after(post, sort, order).encode())
+            .transpose()?
+    } else {
+        None
+    };
+    attach_tags(&pool, &mut items).await?;
+    Ok(Page { items, next_cursor })
+}
+
+/// Turns free text into an FTS5 query: every word must appear, and a
+/// trailing `*` makes a word a prefix match. Each word is quoted so that
+/// FTS5 operators and punctuation in the input are matched literally.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .filter_map(|word| {
+            let (word, prefix) = match word.strip_suffix('*') {
+                Some(word) => (word, "*"),
+                None => (word, ""),
+            };
+            if word.is_empty() {
+                return None;
+            }
+            Some(format!("\"{}\"{prefix}", word.replace('"', "\"\"")))
+        })
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Wrap the matches in FTS5's `highlight` and `snippet` output. They are
+/// private-use characters, which can't be typed into a post by accident,
+/// so that the text can be escaped before the `<mark>` tags go in.
+const MATCH_START: char = '\u{E000}';
+const MATCH_END: char = '\u{E001}';
+
+/// Escapes highlighted text for HTML, then marks the matches. Titles and
+/// bodies are plain text here, so a title like `<script>` must not come
+/// back as markup.
+fn mark_matches(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Full-text search over title, body and author of the posts `viewer` may
+/// see, best matches first.
+#[tracing::instrument(level = "debug", skip(pool, viewer))]
+pub async fn search_blog_posts(
+    pool: sqlx::SqlitePool,
+    query: &SearchQuery,
+    viewer: Option<&User>,
+) -> Result<Vec<SearchHit>> {
+    let Some(fts) = fts_query(&query.q) else {
+        return Err(InvalidQuery("search query is empty".to_string()).into());
+    };
+    let (everything, own) = visibility(viewer);
+    let mut hits = sqlx::query_as::<_, SearchHit>(
+        "SELECT blog_posts.*,
+            bm25(blog_posts_fts) AS rank,
+            highlight(blog_posts_fts, 0, ?, ?) AS title_highlight,
+            snippet(blog_posts_fts, 1, ?, ?, '…', 16) AS snippet
+        FROM blog_posts_fts
+        JOIN blog_posts ON blog_posts.id = blog_posts_fts.rowid
+        WHERE blog_posts_fts MATCH ? AND blog_posts.deleted_at IS NULL
+            AND (? OR blog_posts.status = 'published' OR blog_posts.author = ?)
+        ORDER BY rank
+        LIMIT ?",
+    )
+    .bind(MATCH_START.to_string())
+    .bind(MATCH_END.to_string())
+    .bind(MATCH_START.to_string())
+    .bind(MATCH_END.to_string())
+    .bind(fts)
+    .bind(everything)
+    .bind(own)
+    .bind(query.page_size())
+    .fetch_all(&pool)
+    .await?;
+    for hit in &mut hits {
+        hit.title_highlight = mark_matches(&hit.title_highlight);
+        hit.snippet = mark_matches(&hit.snippet);
+    }
+    attach_tags(&pool, hits.iter_mut().map(|hit| &mut hit.post)).await?;
+    Ok(hits)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` if the post doesn't exist or is in
+/// the trash. Unpublished posts are returned too; it is up to the caller to
+/// check `BlogPost::is_visible_to`.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_blog_post(pool: sqlx::SqlitePool, id: i32) -> Result<BlogPost> {
+    let mut post = sqlx::query_as::<_, BlogPost>("SELECT * FROM blog_posts WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_one(&pool)
+        .await?;
+    attach_tags(&pool, [&mut post]).await?;
+    Ok(post)
+}
+
+#[tracing::instrument(level = "debug", skip(pool, title, body))]
+pub async fn add_blog_post(
+    pool: sqlx::SqlitePool,
+    title: String,
+    body: String,
+    author: String,
+    publication: Publication,
+    tags: &[String],
+) -> Result<i32> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query("INSERT INTO blog_posts (title, body, author, created_at, updated_at, status, publish_at) VALUES (?, ?, ?, ?, ?, ?, ?); SELECT last_insert_rowid();")
+        .bind(title)
+        .bind(body)
+        .bind(author)
+        .bind(now)
+        .bind(now)
+        .bind(publication.status)
+        .bind(publication.publish_at)
+        .fetch_one(&mut *tx)
+        .await?
+        .get(0);
+    set_post_tags(&mut tx, id, tags).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// The post's body rendered to sanitized HTML. The rendering is kept in the
+/// row and reused until `update_blog_post` clears it.
+#[tracing::instrument(level = "debug", skip_all, fields(id = post.id, version = post.version))]
+pub async fn get_rendered_body(pool: sqlx::SqlitePool, post: &BlogPost) -> Result<String> {
+    let cached: Option<String> =
+        sqlx::query_scalar("SELECT body_html FROM blog_posts WHERE id = ? AND version = ?")
+            .bind(post.id)
+            .bind(post.version)
+            .fetch_optional(&pool)
+            .await?
+            .flatten();
+    if let Some(html) = cached {
+        return Ok(html);
+    }
+    let html = render_markdown(&post.body);
+    // Only cache it if the post hasn't been updated since it was read
+    sqlx::query("UPDATE blog_posts SET body_html = ? WHERE id = ? AND version = ?")
+        .bind(&html)
+        .bind(post.id)
+        .bind(post.version)
+        .execute(&pool)
+        .await?;
+    Ok(html)
+}
+
+/// Explains why a conditional write touched no rows.
+async fn missing_or_conflict(pool: sqlx::SqlitePool, id: i32) -> anyhow::Error {
+    let current = sqlx::query("SELECT version FROM blog_posts WHERE id = ? AND deleted_at IS NULL")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await;
+    match current {
+        Ok(Some(row)) => VersionConflict { current: row.get(0) }.into(),
+        Ok(None) => sqlx::Error::RowNotFound.into(),
+        Err(err) => err.into(),
+    }
+}
+
+/// Writes `post`'s title, body, author, publication and tags over the post
+/// with the same id. With `expected_version`, the update only happens if the
+/// post is still at that version, and fails with `VersionConflict`
+/// otherwise. Fails with `sqlx::Error::RowNotFound` if there is no post with
+/// that id, or it is in the trash.
+#[tracing::instrument(level = "debug", skip(pool, post), fields(id = post.id))]
+pub async fn update_blog_post(
+    pool: sqlx::SqlitePool,
+    post: &BlogPost,
+    expected_version: Option<i32>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("UPDATE blog_posts SET title = ?, body = ?, body_html = NULL, author = ?, status = ?, publish_at = ?, updated_at = ?, version = version + 1 WHERE id = ? AND deleted_at IS NULL AND (? IS NULL OR version = ?)")
+        .bind(&post.title)
+        .bind(&post.body)
+        .bind(&post.author)
+        .bind(post.status)
+        .bind(post.publish_at)
+        .bind(Utc::now())
+        .bind(post.id)
+        .bind(expected_version)
+        .bind(expected_version)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        drop(tx);
+        return Err(missing_or_conflict(pool, post.id).await);
+    }
+    set_post_tags(&mut tx, post.id, &post.tags).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Moves a post to the trash, under the same `expected_version` rules as
+/// `update_blog_post`, recording its last version as a `delete` revision.
+/// It stays there until undeleted or purged.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn delete_blog_post(
+    pool: sqlx::SqlitePool,
+    id: i32,
+    expected_version: Option<i32>,
+) -> Result<()> {
+    let result = sqlx::query("UPDATE blog_posts SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL AND (? IS NULL OR version = ?)")
+        .bind(Utc::now())
+        .bind(id)
+        .bind(expected_version)
+        .bind(expected_version)
+        .execute(&pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(missing_or_conflict(pool, id).await);
+    }
+    Ok(())
+}
+
+/// Publishes every scheduled post whose `publish_at` is no later than `now`,
+/// returning how many there were. The content is unchanged, so the version
+/// isn't bumped.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn publish_scheduled_posts(pool: sqlx::SqlitePool, now: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("UPDATE blog_posts SET status = 'published' WHERE status = 'scheduled' AND publish_at <= ? AND deleted_at IS NULL")
+        .bind(now)
+        .execute(&pool)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,29 @@
+use crate::PostRevision;
+use anyhow::Result;
+
+/// Every stored past version of a post, oldest first. A post in the trash
+/// has its last version here too, until it is purged along with its history.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn list_revisions(pool: sqlx::SqlitePool, post_id: i32) -> Result<Vec<PostRevision>> {
+    let revisions = sqlx::query_as::<_, PostRevision>(
+        "SELECT * FROM blog_post_revisions WHERE post_id = ? ORDER BY version",
+    )
+    .bind(post_id)
+    .fetch_all(&pool)
+    .await?;
+    Ok(revisions)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` if that version was never stored.
+/// The current version of a post is not a revision; read the post instead.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_revision(pool: sqlx::SqlitePool, post_id: i32, version: i32) -> Result<PostRevision> {
+    let revision = sqlx::query_as::<_, PostRevision>(
+        "SELECT * FROM blog_post_revisions WHERE post_id = ? AND version = ?",
+    )
+    .bind(post_id)
+    .bind(version)
+    .fetch_one(&pool)
+    .await?;
+    Ok(revision)
+}
@@ -0,0 +1,85 @@
+use super::posts::visibility;
+use crate::{BlogPost, TagCount, User};
+use anyhow::Result;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+
+/// Every tag on at least one post that `viewer` may see, with how many such
+/// posts there are, in name order.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn list_tags(pool: sqlx::SqlitePool, viewer: Option<&User>) -> Result<Vec<TagCount>> {
+    let (everything, own) = visibility(viewer);
+    let tags = sqlx::query_as::<_, TagCount>(
+        "SELECT tags.name, COUNT(*) AS posts
+        FROM tags
+        JOIN post_tags ON post_tags.tag_id = tags.id
+        JOIN blog_posts ON blog_posts.id = post_tags.post_id
+        WHERE blog_posts.deleted_at IS NULL
+            AND (? OR blog_posts.status = 'published' OR blog_posts.author = ?)
+        GROUP BY tags.id
+        ORDER BY tags.name",
+    )
+    .bind(everything)
+    .bind(own)
+    .fetch_all(&pool)
+    .await?;
+    Ok(tags)
+}
+
+/// Fills in the `tags` of each post, with one query for all of them.
+pub(super) async fn attach_tags<'a>(
+    pool: &sqlx::SqlitePool,
+    posts: impl IntoIterator<Item = &'a mut BlogPost>,
+) -> Result<()> {
+    let mut posts: Vec<&mut BlogPost> = posts.into_iter().collect();
+    if posts.is_empty() {
+        return Ok(());
+    }
+    let mut qb = QueryBuilder::<Sqlite>::new(
+        "SELECT post_tags.post_id, tags.name
+        FROM post_tags
+        JOIN tags ON tags.id = post_tags.tag_id
+        WHERE post_tags.post_id IN (",
+    );
+    let mut ids = qb.separated(", ");
+    for post in &posts {
+        ids.push_bind(post.id);
+    }
+    qb.push(") ORDER BY tags.name");
+    let rows = qb.build_query_as::<(i32, String)>().fetch_all(pool).await?;
+
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (post_id, name) in rows {
+        tags.entry(post_id).or_default().push(name);
+    }
+    for post in posts.iter_mut() {
+        post.tags = tags.remove(&post.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Replaces a post's tags, creating any that don't exist yet. Runs on the
+/// caller's connection so that it can share a transaction with the write to
+/// the post itself.
+pub(super) async fn set_post_tags(
+    conn: &mut SqliteConnection,
+    post_id: i32,
+    tags: &[String],
+) -> Result<()> {
+    sqlx::query("DELETE FROM post_tags WHERE post_id = ?")
+        .bind(post_id)
+        .execute(&mut *conn)
+        .await?;
+    for tag in tags {
+        sqlx::query("INSERT INTO tags (name) VALUES (?) ON CONFLICT (name) DO NOTHING")
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("INSERT INTO post_tags (post_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+            .bind(post_id)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
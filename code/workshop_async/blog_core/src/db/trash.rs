@@ -0,0 +1,78 @@
+use super::tags::attach_tags;
+use crate::BlogPost;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+/// Posts in the trash, most recently deleted first. With `author`, only
+/// that author's posts.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn list_deleted_blog_posts(
+    pool: sqlx::SqlitePool,
+    author: Option<&str>,
+) -> Result<Vec<BlogPost>> {
+    let mut posts = sqlx::query_as::<_, BlogPost>(
+        "SELECT * FROM blog_posts
+        WHERE deleted_at IS NOT NULL AND (? IS NULL OR author = ?)
+        ORDER BY deleted_at DESC",
+    )
+    .bind(author)
+    .bind(author)
+    .fetch_all(&pool)
+    .await?;
+    attach_tags(&pool, &mut posts).await?;
+    Ok(posts)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` unless the post is in the trash.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_deleted_blog_post(pool: sqlx::SqlitePool, id: i32) -> Result<BlogPost> {
+    let mut post = sqlx::query_as::<_, BlogPost>(
+        "SELECT * FROM blog_posts WHERE id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(id)
+    .fetch_one(&pool)
+    .await?;
+    attach_tags(&pool, [&mut post]).await?;
+    Ok(post)
+}
+
+/// Takes a post back out of the trash. Fails with `sqlx::Error::RowNotFound`
+/// unless it was in there.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn undelete_blog_post(pool: sqlx::SqlitePool, id: i32) -> Result<()> {
+    let result = sqlx::query(
+        "UPDATE blog_posts SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+    )
+    .bind(id)
+    .execute(&pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
+
+/// Permanently removes one post from the trash, and its history with it.
+/// Fails with `sqlx::Error::RowNotFound` unless the post was in the trash.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn purge_blog_post(pool: sqlx::SqlitePool, id: i32) -> Result<()> {
+    let result = sqlx::query("DELETE FROM blog_posts WHERE id = ? AND deleted_at IS NOT NULL")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
+
+/// Permanently removes every post that went into the trash before `cutoff`,
+/// and their history, returning how many were purged.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn purge_deleted_blog_posts(pool: sqlx::SqlitePool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM blog_posts WHERE deleted_at IS NOT NULL AND deleted_at < ?")
+        .bind(cutoff)
+        .execute(&pool)
+        .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,106 @@
+use crate::User;
+use anyhow::Result;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+
+/// A freshly issued API token. `token` is the only copy of the secret.
+#[derive(Debug)]
+pub struct IssuedToken {
+    pub id: i32,
+    pub token: String,
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn add_user(pool: sqlx::SqlitePool, name: String, is_admin: bool) -> Result<i32> {
+    let id = sqlx::query("INSERT INTO users (name, is_admin) VALUES (?, ?); SELECT last_insert_rowid();")
+        .bind(name)
+        .bind(is_admin)
+        .fetch_one(&pool)
+        .await?
+        .get(0);
+    Ok(id)
+}
+
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn get_user_by_name(pool: sqlx::SqlitePool, name: &str) -> Result<User> {
+    let user = sqlx::query_as::<_, User>("SELECT id, name, is_admin FROM users WHERE name = ?")
+        .bind(name)
+        .fetch_one(&pool)
+        .await?;
+    Ok(user)
+}
+
+/// Whether a registered user has this name, in any mix of case, so that
+/// anonymous commenters can't pass themselves off as `Alice` by writing
+/// `alice`.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn user_name_taken(pool: sqlx::SqlitePool, name: &str) -> Result<bool> {
+    let taken = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM users WHERE name = ? COLLATE NOCASE)",
+    )
+    .bind(name)
+    .fetch_one(&pool)
+    .await?;
+    Ok(taken)
+}
+
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn list_users(pool: sqlx::SqlitePool) -> Result<Vec<User>> {
+    let users = sqlx::query_as::<_, User>("SELECT id, name, is_admin FROM users ORDER BY id")
+        .fetch_all(&pool)
+        .await?;
+    Ok(users)
+}
+
+/// Creates a new random token for the user. Only its hash is stored.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn issue_token(
+    pool: sqlx::SqlitePool,
+    user_id: i32,
+    label: Option<String>,
+) -> Result<IssuedToken> {
+    let mut secret = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    let token = format!("blog_{}", hex::encode(secret));
+    let id = sqlx::query("INSERT INTO api_tokens (user_id, token_hash, label) VALUES (?, ?, ?); SELECT last_insert_rowid();")
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(label)
+        .fetch_one(&pool)
+        .await?
+        .get(0);
+    Ok(IssuedToken { id, token })
+}
+
+/// Looks up the owner of a bearer token, or `None` if it isn't valid.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn authenticate_token(pool: sqlx::SqlitePool, token: &str) -> Result<Option<User>> {
+    let user = sqlx::query_as::<_, User>(
+        "SELECT users.id, users.name, users.is_admin
+        FROM api_tokens
+        JOIN users ON users.id = api_tokens.user_id
+        WHERE api_tokens.token_hash = ?",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&pool)
+    .await?;
+    Ok(user)
+}
+
+/// Fails with `sqlx::Error::RowNotFound` if there is no token with that id.
+#[tracing::instrument(level = "debug", skip(pool))]
+pub async fn revoke_token(pool: sqlx::SqlitePool, id: i32) -> Result<()> {
+    let result = sqlx::query("DELETE FROM api_tokens WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
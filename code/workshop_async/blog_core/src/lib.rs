@@ -0,0 +1,35 @@
+//! The blog post model and data access shared by the workshop crates.
+//!
+//! `db_setup`, `axum_setup` and `blog_client` all compile against the
+//! definitions here, so a schema change only has to be made once. Post
+//! bodies are CommonMark; the `markdown` feature renders them to HTML.
+
+mod comment;
+#[cfg(feature = "db")]
+pub mod db;
+mod listing;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod post;
+mod revision;
+mod tag;
+mod user;
+mod validation;
+
+pub use comment::{Comment, CommentQuery, CommentStatus, CreateComment, ModerateComment};
+pub use listing::{
+    ListQuery, Page, SearchQuery, SortField, SortOrder, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+#[cfg(feature = "markdown")]
+pub use markdown::render_markdown;
+pub use post::{
+    BlogPost, CreatePost, PatchPost, PostFormat, PostQuery, PostStatus, Publication, SearchHit,
+    UpdatePost,
+};
+pub use revision::{DiffQuery, PostRevision, RevisionDiff, RevisionOperation};
+pub use tag::TagCount;
+pub use user::User;
+pub use validation::{
+    validate_author, FieldError, MAX_AUTHOR_LEN, MAX_BODY_LEN, MAX_COMMENT_LEN, MAX_TAGS,
+    MAX_TAG_LEN, MAX_TITLE_LEN,
+};
@@ -0,0 +1,11 @@
+use pulldown_cmark::{html, Parser};
+
+/// Renders a post body, written in CommonMark, to HTML that is safe to put
+/// in a page. Raw HTML in the body only survives as far as the sanitizer
+/// allows: scripts, styles, event handlers and `javascript:` links are
+/// stripped.
+pub fn render_markdown(text: &str) -> String {
+    let mut html = String::new();
+    html::push_html(&mut html, Parser::new(text));
+    ammonia::clean(&html)
+}
@@ -0,0 +1,318 @@
+use crate::validation::{FieldError, Validator, MAX_BODY_LEN, MAX_TITLE_LEN};
+use crate::User;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a post is in its life. Only published posts are shown to readers
+/// who aren't signed in; a scheduled post is published by the server once
+/// its `publish_at` time has passed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "db", sqlx(rename_all = "lowercase"))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum PostStatus {
+    Draft,
+    Scheduled,
+    #[default]
+    Published,
+    Archived,
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+            PostStatus::Archived => "archived",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for PostStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(PostStatus::Draft),
+            "scheduled" => Ok(PostStatus::Scheduled),
+            "published" => Ok(PostStatus::Published),
+            "archived" => Ok(PostStatus::Archived),
+            _ => Err(format!(
+                "unknown status `{s}` (expected draft, scheduled, published or archived)"
+            )),
+        }
+    }
+}
+
+/// A post's status, and when it was (or will be) published.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Publication {
+    pub status: PostStatus,
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+impl Publication {
+    /// Works out where a write leaves a post, given the `status` and
+    /// `publish_at` a request asked for and the post's `current` publication
+    /// (`None` for a new post). Anything not asked for stays as it is; new
+    /// posts are published straight away. `publish_at` may only be given for
+    /// scheduled posts, and is stamped with `now` when a post is published.
+    pub fn resolve(
+        current: Option<Publication>,
+        status: Option<PostStatus>,
+        publish_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, Vec<FieldError>> {
+        let mut v = Validator::default();
+        let status = status
+            .or(current.map(|current| current.status))
+            .unwrap_or(PostStatus::Published);
+        // A new post is treated as an unpublished draft being moved on
+        let current = current.unwrap_or(Publication {
+            status: PostStatus::Draft,
+            publish_at: None,
+        });
+        if status != PostStatus::Scheduled && publish_at.is_some() {
+            v.fail("publish_at", "may only be set when status is scheduled");
+        }
+        let publish_at = match status {
+            PostStatus::Scheduled => {
+                let at = publish_at.or(match current.status {
+                    PostStatus::Scheduled => current.publish_at,
+                    _ => None,
+                });
+                if at.is_none() {
+                    v.fail("publish_at", "is required when status is scheduled");
+                }
+                at
+            }
+            PostStatus::Published if current.status == PostStatus::Published => current.publish_at,
+            PostStatus::Published => Some(now),
+            PostStatus::Draft => None,
+            PostStatus::Archived => current.publish_at,
+        };
+        v.finish(Publication { status, publish_at })
+    }
+}
+
+/// A single row of the `blog_posts` table. The timestamps are set by the
+/// server and serialized as RFC 3339; clients may leave them out. `version`
+/// goes up by one on every update. `deleted_at` is only set on posts in the
+/// trash. `publish_at` is when the post went public or, if it is scheduled,
+/// when it will.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BlogPost {
+    pub id: i32,
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub status: PostStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Kept in `post_tags`, and filled in by the `db` functions that return
+    /// posts.
+    #[serde(default)]
+    #[cfg_attr(feature = "db", sqlx(skip))]
+    pub tags: Vec<String>,
+}
+
+impl BlogPost {
+    pub fn publication(&self) -> Publication {
+        Publication {
+            status: self.status,
+            publish_at: self.publish_at,
+        }
+    }
+
+    /// When the post went out, or is due to. Older posts predate scheduling,
+    /// and were published as soon as they were created.
+    pub fn published_at(&self) -> DateTime<Utc> {
+        self.publish_at.unwrap_or(self.created_at)
+    }
+
+    /// Published posts are public; anything else is only visible to the
+    /// people who may edit it.
+    pub fn is_visible_to(&self, viewer: Option<&User>) -> bool {
+        self.status == PostStatus::Published
+            || viewer.is_some_and(|user| user.can_edit(&self.author))
+    }
+}
+
+/// How a single post is sent back: as JSON, or as its body rendered to HTML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum PostFormat {
+    Json,
+    Html,
+}
+
+/// Query string for reading a single post. Without `format`, the `Accept`
+/// header decides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct PostQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<PostFormat>,
+}
+
+/// A post matched by a full-text search. `title_highlight` and `snippet`
+/// are HTML: the text is escaped and matched terms are wrapped in `<mark>`
+/// tags. Lower `rank` is better.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchHit {
+    #[serde(flatten)]
+    #[cfg_attr(feature = "db", sqlx(flatten))]
+    pub post: BlogPost,
+    pub rank: f64,
+    pub title_highlight: String,
+    pub snippet: String,
+}
+
+/// Body of a request to create a post. Missing fields deserialize as empty
+/// so that `validate` can report all of them at once.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatePost {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    /// Defaults to the authenticated user. Only admins may name someone else.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Defaults to published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<PostStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl CreatePost {
+    /// Returns the post with whitespace trimmed, or every failing field.
+    pub fn validate(self) -> Result<Self, Vec<FieldError>> {
+        let mut v = Validator::default();
+        let post = CreatePost {
+            title: v.line("title", &self.title, MAX_TITLE_LEN),
+            body: v.text("body", &self.body, MAX_BODY_LEN),
+            author: self.author.map(|author| v.author("author", &author)),
+            status: self.status,
+            publish_at: self.publish_at,
+            tags: v.tags("tags", &self.tags),
+        };
+        v.finish(post)
+    }
+}
+
+/// Body of a request to replace a post's title and body. The status and tags
+/// are only changed if given.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdatePost {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<PostStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl UpdatePost {
+    /// Returns the post with whitespace trimmed, or every failing field.
+    pub fn validate(self) -> Result<Self, Vec<FieldError>> {
+        let mut v = Validator::default();
+        let post = UpdatePost {
+            title: v.line("title", &self.title, MAX_TITLE_LEN),
+            body: v.text("body", &self.body, MAX_BODY_LEN),
+            status: self.status,
+            publish_at: self.publish_at,
+            tags: self.tags.map(|tags| v.tags("tags", &tags)),
+        };
+        v.finish(post)
+    }
+
+    /// The post as it will be once this update is written: `current` with
+    /// the new title, body and publication, and the new tags if any were
+    /// given.
+    pub fn merge(self, current: &BlogPost, publication: Publication) -> BlogPost {
+        BlogPost {
+            title: self.title,
+            body: self.body,
+            status: publication.status,
+            publish_at: publication.publish_at,
+            tags: self.tags.unwrap_or_else(|| current.tags.clone()),
+            ..current.clone()
+        }
+    }
+}
+
+/// Body of a PATCH request: only the fields present are changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PatchPost {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<PostStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+impl PatchPost {
+    /// Returns the patch with whitespace trimmed, or every failing field.
+    pub fn validate(self) -> Result<Self, Vec<FieldError>> {
+        let mut v = Validator::default();
+        let patch = PatchPost {
+            title: self.title.map(|title| v.line("title", &title, MAX_TITLE_LEN)),
+            body: self.body.map(|body| v.text("body", &body, MAX_BODY_LEN)),
+            status: self.status,
+            publish_at: self.publish_at,
+            tags: self.tags.map(|tags| v.tags("tags", &tags)),
+        };
+        v.finish(patch)
+    }
+
+    /// Applies the patch on top of the current title and body. The status and
+    /// tags are passed through, to be resolved against the post's current ones.
+    pub fn apply(self, current: &BlogPost) -> UpdatePost {
+        UpdatePost {
+            title: self.title.unwrap_or_else(|| current.title.clone()),
+            body: self.body.unwrap_or_else(|| current.body.clone()),
+            status: self.status,
+            publish_at: self.publish_at,
+            tags: self.tags,
+        }
+    }
+}
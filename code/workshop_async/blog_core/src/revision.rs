@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::Type))]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "db", sqlx(rename_all = "lowercase"))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum RevisionOperation {
+    Update,
+    Delete,
+}
+
+/// A past version of a post, as it was before `operation` replaced it.
+/// `updated_at` is when this version was written, `revised_at` when it
+/// stopped being current.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PostRevision {
+    pub post_id: i32,
+    pub version: i32,
+    pub title: String,
+    pub body: String,
+    pub author: String,
+    pub updated_at: DateTime<Utc>,
+    pub revised_at: DateTime<Utc>,
+    pub operation: RevisionOperation,
+}
+
+/// Unified diffs of the title and body between two versions of a post.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RevisionDiff {
+    pub post_id: i32,
+    pub from: i32,
+    pub to: i32,
+    pub title: String,
+    pub body: String,
+}
+
+/// Query string for the diff route. `to` defaults to the current version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct DiffQuery {
+    pub from: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<i32>,
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// A tag, and how many posts carry it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TagCount {
+    pub name: String,
+    pub posts: i64,
+}
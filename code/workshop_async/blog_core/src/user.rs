@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Someone who can write posts. A user owns the posts whose `author`
+/// matches their `name`; admins may edit anyone's posts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "db", derive(sqlx::FromRow))]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+    pub is_admin: bool,
+}
+
+impl User {
+    pub fn can_edit(&self, author: &str) -> bool {
+        self.is_admin || self.name == author
+    }
+}
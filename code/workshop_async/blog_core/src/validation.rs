@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+pub const MAX_TITLE_LEN: usize = 200;
+pub const MAX_BODY_LEN: usize = 64 * 1024;
+pub const MAX_AUTHOR_LEN: usize = 64;
+pub const MAX_COMMENT_LEN: usize = 8 * 1024;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_TAGS: usize = 10;
+
+/// A single field that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects every failing field, rather than stopping at the first.
+#[derive(Debug, Default)]
+pub(crate) struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub(crate) fn fail(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Trims `value` and checks it is present and no longer than `max` characters.
+    pub(crate) fn text(&mut self, field: &str, value: &str, max: usize) -> String {
+        let value = value.trim();
+        if value.is_empty() {
+            self.fail(field, "is required");
+        } else if value.chars().count() > max {
+            self.fail(field, format!("must be at most {max} characters"));
+        }
+        value.to_string()
+    }
+
+    /// A single line of text: like `text`, but control characters are refused.
+    pub(crate) fn line(&mut self, field: &str, value: &str, max: usize) -> String {
+        let value = self.text(field, value, max);
+        if value.chars().any(char::is_control) {
+            self.fail(field, "must not contain control characters");
+        }
+        value
+    }
+
+    pub(crate) fn author(&mut self, field: &str, value: &str) -> String {
+        let value = self.text(field, value, MAX_AUTHOR_LEN);
+        if let Err(message) = check_author_chars(&value) {
+            self.fail(field, message);
+        }
+        value
+    }
+
+    /// Tags are lowercased, de-duplicated and sorted. Each one may only use
+    /// letters, digits, `-` and `_`, so that it can go in a URL as it is.
+    pub(crate) fn tags(&mut self, field: &str, tags: &[String]) -> Vec<String> {
+        let mut tags: Vec<String> = tags
+            .iter()
+            .map(|tag| self.text(field, tag, MAX_TAG_LEN).to_lowercase())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        let allowed = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_');
+        if tags.iter().any(|tag| !tag.chars().all(allowed)) {
+            self.fail(field, "tags may only contain letters, digits, - and _");
+        }
+        if tags.len() > MAX_TAGS {
+            self.fail(field, format!("must have at most {MAX_TAGS} tags"));
+        }
+        tags
+    }
+
+    pub(crate) fn finish<T>(self, value: T) -> Result<T, Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+fn check_author_chars(name: &str) -> Result<(), &'static str> {
+    let allowed = |c: char| c.is_alphanumeric() || matches!(c, ' ' | '.' | '-' | '_' | '\'');
+    if name.chars().all(allowed) {
+        Ok(())
+    } else {
+        Err("may only contain letters, digits, spaces and . - _ '")
+    }
+}
+
+/// Checks a user or author name, returning it trimmed. User names become
+/// post authors, so both follow the same rules.
+pub fn validate_author(name: &str) -> Result<String, Vec<FieldError>> {
+    let mut v = Validator::default();
+    let name = v.author("name", name);
+    v.finish(name)
+}
@@ -1,7 +0,0 @@
-CREATE TABLE blog_posts (
-    id INTEGER PRIMARY KEY AUTOINCREMENT,
-    date TEXT NOT NULL,
-    title TEXT,
-    body TEXT,
-    author TEXT
-);
\ No newline at end of file
@@ -1,3 +0,0 @@
-INSERT INTO blog_posts (date, title, body, author) VALUES
-    ('2021-01-01', 'A Tale of Two Cities', 'It was the best of times, it was the worst of times.', 'Dickens'),
-    ('2021-01-02', 'Moby Dick', 'Call me Ishmael.', 'Melville');
\ No newline at end of file